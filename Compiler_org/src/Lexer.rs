@@ -1,11 +1,13 @@
-use std::env;
-use std::fs::File;
-use std::io::{self, Read}; // Fix for missing imports
-use std::process;
-#[derive(Debug,Clone, PartialEq)]
+use crate::diagnostics::Diagnostic;
+use crate::types::{FloatSuffix, IntSuffix, Radix};
 
+#[derive(Debug,Clone, PartialEq)]
+#[allow(dead_code)] // some tokens are reserved for later
 pub enum Token {
-    Number(f64),
+    IntLiteral { value: u64, radix: Radix, suffix: IntSuffix },
+    FloatLiteral { value: f64, suffix: FloatSuffix },
+    CharLiteral(u8),
+    StringLiteral(Vec<u8>), // the bytes after escapes are processed, without the terminating 0
     Plus,
     Minus,
     Star,
@@ -38,11 +40,135 @@ pub enum Token {
 
     Colon,
     Question,
+
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    ShiftLeft,
+    ShiftRight,
+    Increment,
+    Decrement,
+
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    PercentAssign,
+    AndAssign,
+    OrAssign,
+    XorAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
+
+    Comma,
+    Dot,
+    Arrow,
+    LBracket,
+    RBracket,
+    Ellipsis,
+    Hash,
+    HashHash,
+}
+
+
+// every C11 punctuator (6.4.6), digraphs included. longer ones come first,
+// so the first entry the input starts with is the maximal munch
+const PUNCTUATORS: &[(&str, Token)] = &[
+    ("%:%:", Token::HashHash),
+    ("...", Token::Ellipsis),
+    ("<<=", Token::ShiftLeftAssign),
+    (">>=", Token::ShiftRightAssign),
+    ("->", Token::Arrow),
+    ("++", Token::Increment),
+    ("--", Token::Decrement),
+    ("<<", Token::ShiftLeft),
+    (">>", Token::ShiftRight),
+    ("<=", Token::LessEq),
+    (">=", Token::GreatThEq),
+    ("==", Token::EqualTo),
+    ("!=", Token::NEqualTo),
+    ("&&", Token::LogAnd),
+    ("||", Token::LogOr),
+    ("*=", Token::StarAssign),
+    ("/=", Token::SlashAssign),
+    ("%=", Token::PercentAssign),
+    ("+=", Token::PlusAssign),
+    ("-=", Token::MinusAssign),
+    ("&=", Token::AndAssign),
+    ("^=", Token::XorAssign),
+    ("|=", Token::OrAssign),
+    ("##", Token::HashHash),
+    ("<:", Token::LBracket),
+    (":>", Token::RBracket),
+    ("<%", Token::LBrace),
+    ("%>", Token::RBrace),
+    ("%:", Token::Hash),
+    ("[", Token::LBracket),
+    ("]", Token::RBracket),
+    ("(", Token::LParen),
+    (")", Token::RParen),
+    ("{", Token::LBrace),
+    ("}", Token::RBrace),
+    (".", Token::Dot),
+    ("&", Token::Ampersand),
+    ("*", Token::Star),
+    ("+", Token::Plus),
+    ("-", Token::Minus),
+    ("~", Token::bitwise),
+    ("!", Token::logical),
+    ("/", Token::Slash),
+    ("%", Token::Percent),
+    ("<", Token::Less),
+    (">", Token::GreatTh),
+    ("^", Token::Caret),
+    ("|", Token::Pipe),
+    ("?", Token::Question),
+    (":", Token::Colon),
+    (";", Token::Semi),
+    ("=", Token::Assign),
+    (",", Token::Comma),
+    ("#", Token::Hash),
+];
+
+
+// Where something lives in the source file.
+// line and column are 1-based (column counts chars), start/end are byte offsets with end exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // covers everything from the start of self to the end of other
+    pub fn to(self, other: Span) -> Span {
+        Span { end: other.end.max(self.start), ..self }
+    }
+
+    // empty span right after a single line token, where a missing ';' would go
+    pub fn after(self) -> Span {
+        Span { line: self.line, column: self.column + (self.end - self.start), start: self.end, end: self.end }
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
 }
 
+
 pub struct Lexer {
     input: Vec<char>,
     pos: usize,
+    byte_pos: usize,
+    line: usize,
+    column: usize,
+    token_start: Span,
 }
 
 impl Lexer {
@@ -50,6 +176,10 @@ impl Lexer {
         Self {
             input: input.chars().collect(),
             pos: 0,
+            byte_pos: 0,
+            line: 1,
+            column: 1,
+            token_start: Span::default(),
         }
     }
 
@@ -57,12 +187,29 @@ impl Lexer {
         if self.pos < self.input.len() {
             let ch = self.input[self.pos];
             self.pos += 1;
+            self.byte_pos += ch.len_utf8();
+            if ch == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
             Some(ch)
         } else {
             None
         }
     }
 
+    // position of the next char, used as the start of a token
+    fn here(&self) -> Span {
+        Span { line: self.line, column: self.column, start: self.byte_pos, end: self.byte_pos }
+    }
+
+    // error covering the token lexed so far
+    fn error(&self, code: &'static str, message: String) -> Diagnostic {
+        Diagnostic::error(message, Span { end: self.byte_pos, ..self.token_start }).with_code(code)
+    }
+
     fn peek_char(&self) -> Option<char> {
         if self.pos < self.input.len() {
             Some(self.input[self.pos])
@@ -71,21 +218,302 @@ impl Lexer {
         }
     }
 
-    fn lex_number(&mut self, first_digit: char) -> Token {
-        let mut num_str = first_digit.to_string();
+    // one char further than peek_char, needed to tell "/*" and "//" from "/"
+    fn peek_second_char(&self) -> Option<char> {
+        self.input.get(self.pos + 1).copied()
+    }
+
+    // whitespace and comments between tokens
+    fn skip_trivia(&mut self) -> Result<(), Diagnostic> {
+        loop {
+            match (self.peek_char(), self.peek_second_char()) {
+                (Some(' ' | '\t' | '\n' | '\r'), _) => {
+                    self.next_char();
+                }
+                (Some('/'), Some('/')) => {
+                    while let Some(ch) = self.peek_char() {
+                        if ch == '\n' {
+                            break;
+                        }
+                        self.next_char();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    self.token_start = self.here();
+                    self.next_char();
+                    self.next_char();
+                    loop {
+                        match self.next_char() {
+                            Some('*') if self.peek_char() == Some('/') => {
+                                self.next_char();
+                                break;
+                            }
+                            Some(_) => {}
+                            None => {
+                                // point at the opening "/*", not at the end of the file
+                                let open = Span { end: self.token_start.start + 2, ..self.token_start };
+                                return Err(Diagnostic::error("unterminated /* comment", open).with_code("E0104"));
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn lex_number(&mut self, first_digit: char) -> Result<Token, Diagnostic> {
+        let prefix = self.peek_char().map(|c| c.to_ascii_lowercase());
+        let radix = match (first_digit, prefix) {
+            ('0', Some('x')) => Radix::Hex,
+            ('0', Some('b')) => Radix::Binary,
+            ('0', _) => Radix::Octal,
+            _ => Radix::Decimal,
+        };
+        if matches!(radix, Radix::Hex | Radix::Binary) {
+            self.next_char(); // the x or b
+        }
+
+        let mut num_str = String::new();
+        if matches!(radix, Radix::Decimal | Radix::Octal) {
+            num_str.push(first_digit);
+        }
         while let Some(ch) = self.peek_char() {
-            if ch.is_numeric() || ch == '.' {
+            if ch.is_ascii_hexdigit() && (radix == Radix::Hex || ch.is_ascii_digit()) {
                 num_str.push(self.next_char().unwrap());
             } else {
                 break;
             }
         }
-        
-        // Improved error handling for number parsing
-        match num_str.parse::<f64>() {
-            Ok(num) => Token::Number(num),
-            Err(_) => panic!("Failed to parse number: {}", num_str), // You can handle the error more gracefully
+
+        // a '.' or an exponent makes it a floating literal
+        let is_float = match radix {
+            Radix::Hex => matches!(self.peek_char(), Some('.' | 'p' | 'P')),
+            Radix::Binary => false,
+            Radix::Decimal | Radix::Octal => matches!(self.peek_char(), Some('.' | 'e' | 'E')),
+        };
+        if is_float {
+            return self.lex_float(num_str, radix == Radix::Hex);
+        }
+
+        let base = match radix {
+            Radix::Decimal => 10,
+            Radix::Octal => 8,
+            Radix::Hex => 16,
+            Radix::Binary => 2,
+        };
+        if num_str.is_empty() {
+            return Err(self.error("E0105", "missing digits after the radix prefix".to_string()));
+        }
+        let mut value: u64 = 0;
+        for digit in num_str.chars() {
+            let digit_value = digit.to_digit(16).unwrap();
+            if digit_value >= base {
+                return Err(self.error("E0106", format!("invalid digit '{}' in {} constant", digit, radix_name(radix))));
+            }
+            value = match value.checked_mul(base as u64).and_then(|v| v.checked_add(digit_value as u64)) {
+                Some(value) => value,
+                None => return Err(self.error("E0107", "integer literal is too large to be represented in any integer type".to_string())),
+            };
         }
+
+        let suffix = self.lex_int_suffix()?;
+        Ok(Token::IntLiteral { value, radix, suffix })
+    }
+
+    // the rest of a floating literal once we know it is one: fraction, exponent and suffix.
+    // `digits` holds what lex_number already read before the '.' or exponent
+    fn lex_float(&mut self, mut digits: String, hex: bool) -> Result<Token, Diagnostic> {
+        let mut fraction_digits = 0;
+        if self.peek_char() == Some('.') {
+            self.next_char();
+            while let Some(ch) = self.peek_char() {
+                if ch.is_ascii_digit() || (hex && ch.is_ascii_hexdigit()) {
+                    digits.push(self.next_char().unwrap());
+                    fraction_digits += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        if digits.is_empty() {
+            return Err(self.error("E0105", "missing digits in floating constant".to_string()));
+        }
+
+        // decimal exponents are optional and scale by 10, hex ones are required and scale by 2
+        let exponent_char = if hex { 'p' } else { 'e' };
+        let mut exponent: i32 = 0;
+        if self.peek_char().map(|c| c.to_ascii_lowercase()) == Some(exponent_char) {
+            self.next_char();
+            let mut exponent_str = String::new();
+            if let Some(sign @ ('+' | '-')) = self.peek_char() {
+                exponent_str.push(sign);
+                self.next_char();
+            }
+            while let Some(ch) = self.peek_char() {
+                if ch.is_ascii_digit() {
+                    exponent_str.push(self.next_char().unwrap());
+                } else {
+                    break;
+                }
+            }
+            // out of range exponents just saturate, the value becomes inf or 0 like with strtod
+            exponent = match exponent_str.parse::<i64>() {
+                Ok(value) => value.clamp(-100_000, 100_000) as i32,
+                Err(_) if exponent_str.trim_start_matches(['+', '-']).is_empty() => {
+                    return Err(self.error("E0109", "exponent has no digits".to_string()));
+                }
+                Err(_) => if exponent_str.starts_with('-') { -100_000 } else { 100_000 },
+            };
+        } else if hex {
+            return Err(self.error("E0109", "hexadecimal floating constant requires an exponent".to_string()));
+        }
+
+        let value = if hex {
+            let mut mantissa = 0.0f64;
+            for digit in digits.chars() {
+                mantissa = mantissa * 16.0 + digit.to_digit(16).unwrap() as f64;
+            }
+            mantissa * 2f64.powi(exponent - 4 * fraction_digits)
+        } else {
+            let (integer, fraction) = digits.split_at(digits.len() - fraction_digits as usize);
+            let text = format!("0{}.{}0e{}", integer, fraction, exponent);
+            match text.parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => return Err(self.error("E0102", format!("Failed to parse number: {}", text))),
+            }
+        };
+
+        let mut text = String::new();
+        while let Some(ch) = self.peek_char() {
+            if ch.is_alphanumeric() || ch == '_' {
+                text.push(self.next_char().unwrap());
+            } else {
+                break;
+            }
+        }
+        let suffix = match text.as_str() {
+            "" => FloatSuffix::None,
+            "f" | "F" => FloatSuffix::F,
+            "l" | "L" => FloatSuffix::L,
+            _ => return Err(self.error("E0108", format!("invalid suffix '{}' on floating constant", text))),
+        };
+        Ok(Token::FloatLiteral { value, suffix })
+    }
+
+    // 'a', '\n', '\x41'. the opening quote is already eaten
+    fn lex_char(&mut self) -> Result<Token, Diagnostic> {
+        let bytes = self.lex_quoted('\'')?;
+        match bytes.as_slice() {
+            [byte] => Ok(Token::CharLiteral(*byte)),
+            [] => Err(self.error("E0112", "empty character constant".to_string())),
+            _ => Err(self.error("E0112", "character constant holds more than one char".to_string())),
+        }
+    }
+
+    // "hello\n". the opening quote is already eaten
+    fn lex_string(&mut self) -> Result<Token, Diagnostic> {
+        Ok(Token::StringLiteral(self.lex_quoted('"')?))
+    }
+
+    // the bytes up to the closing quote. a bad escape doesn't stop us there, so lexing
+    // goes on after the literal instead of in the middle of it
+    fn lex_quoted(&mut self, quote: char) -> Result<Vec<u8>, Diagnostic> {
+        let mut bytes = Vec::new();
+        let mut bad_escape = None;
+        loop {
+            match self.next_char() {
+                Some(ch) if ch == quote => break,
+                Some('\\') => match self.lex_escape() {
+                    Ok(byte) => bytes.push(byte),
+                    Err(err) => bad_escape = bad_escape.or(Some(err)),
+                },
+                Some('\n') | None => return Err(self.error("E0110", format!("missing terminating {} character", quote))),
+                Some(ch) => bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        match bad_escape {
+            Some(err) => Err(err),
+            None => Ok(bytes),
+        }
+    }
+
+    // the part of an escape sequence after the backslash
+    fn lex_escape(&mut self) -> Result<u8, Diagnostic> {
+        let ch = match self.next_char() {
+            Some(ch) => ch,
+            None => return Err(self.error("E0110", "unterminated escape sequence".to_string())),
+        };
+        match ch {
+            'n' => Ok(b'\n'),
+            't' => Ok(b'\t'),
+            'r' => Ok(b'\r'),
+            'a' => Ok(0x07),
+            'b' => Ok(0x08),
+            'f' => Ok(0x0c),
+            'v' => Ok(0x0b),
+            'e' => Ok(0x1b), // GNU
+            '\\' | '\'' | '"' | '?' => Ok(ch as u8),
+            // up to three octal digits, \0 is the common case
+            '0'..='7' => {
+                let mut value = ch.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match self.peek_char().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            self.next_char();
+                            value = value * 8 + digit;
+                        }
+                        None => break,
+                    }
+                }
+                u8::try_from(value).map_err(|_| self.error("E0111", "octal escape sequence out of range".to_string()))
+            }
+            // as many hex digits as there are
+            'x' => {
+                let mut value: u32 = 0;
+                let mut digits = 0;
+                while let Some(digit) = self.peek_char().and_then(|c| c.to_digit(16)) {
+                    self.next_char();
+                    value = value.saturating_mul(16).saturating_add(digit);
+                    digits += 1;
+                }
+                if digits == 0 {
+                    return Err(self.error("E0111", "\\x used with no following hex digits".to_string()));
+                }
+                u8::try_from(value).map_err(|_| self.error("E0111", "hex escape sequence out of range".to_string()))
+            }
+            _ => Err(self.error("E0111", format!("unknown escape sequence '\\{}'", ch))),
+        }
+    }
+
+    // u, l, ll in either order and any case, but no mixed "lL"
+    fn lex_int_suffix(&mut self) -> Result<IntSuffix, Diagnostic> {
+        let mut text = String::new();
+        while let Some(ch) = self.peek_char() {
+            if ch.is_alphanumeric() || ch == '_' {
+                text.push(self.next_char().unwrap());
+            } else {
+                break;
+            }
+        }
+
+        let mut suffix = IntSuffix::default();
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            if !suffix.unsigned && (rest.starts_with('u') || rest.starts_with('U')) {
+                suffix.unsigned = true;
+                rest = &rest[1..];
+            } else if suffix.longs == 0 && (rest.starts_with("ll") || rest.starts_with("LL")) {
+                suffix.longs = 2;
+                rest = &rest[2..];
+            } else if suffix.longs == 0 && (rest.starts_with('l') || rest.starts_with('L')) {
+                suffix.longs = 1;
+                rest = &rest[1..];
+            } else {
+                return Err(self.error("E0108", format!("invalid suffix '{}' on integer constant", text)));
+            }
+        }
+        Ok(suffix)
     }
 
 
@@ -95,12 +523,6 @@ impl Lexer {
         while let Some(ch) = self.peek_char() {
             if ch.is_alphanumeric() || ch == '_' {
                 ident_str.push(self.next_char().unwrap());
-            } else if ch.is_numeric() {
-                ident_str.push(self.next_char().unwrap());
-            }
-            else if ch == '&' || ch == '|'{
-                ident_str.push(self.next_char().unwrap());
-                break;
             }
             else
             {
@@ -110,57 +532,154 @@ impl Lexer {
         
         // Handle keywords
         match ident_str.as_str() {
-            "int" | "return" | "if" | "else" => Token::Keyword(ident_str),
-            "&&" => Token::LogAnd,
-            "||" => Token::LogOr,
+            "void" | "char" | "short" | "int" | "long" | "signed" | "unsigned" | "float" | "double"
+            | "return" | "if" | "else"
+            | "while" | "do" | "for" | "break" | "continue" | "switch" | "case" | "default"
+            | "static" | "extern" | "const" | "typedef" | "struct" | "union" | "enum" => Token::Keyword(ident_str),
             _ => Token::Ident(ident_str),
         }
     }
 
-    fn lex_identifier_then(&mut self , first_char : char) -> Token {
-        let mut ident_str = first_char.to_string();
-        let mut ch = self.peek_char();
-        
-        if ch == Some('=') {
-            ident_str.push(self.next_char().unwrap());
+    // the longest punctuator starting with first_char, which is already eaten
+    fn lex_punctuator(&mut self, first_char: char) -> Option<Token> {
+        let rest: String = std::iter::once(first_char).chain(self.input[self.pos..].iter().take(3).copied()).collect();
+        let (text, token) = PUNCTUATORS.iter().find(|(text, _)| rest.starts_with(text))?;
+        for _ in 1..text.len() {
+            self.next_char();
         }
-        
-        match ident_str.as_str() {
-            "==" => Token::EqualTo,
-            "!=" => Token::NEqualTo,
-            ">=" => Token::GreatThEq,
-            "<=" => Token::LessEq,
-            "=" => Token::Assign,
-            "!" => Token::logical,
-            ">" => Token::GreatTh,
-            "<" => Token::Less,
-            _ => panic!("Unknown"),
+        Some(token.clone())
+    }
+
+
+    // the next token, and the error if it was malformed. a bad token still comes back as the
+    // closest good one (0 for a bad number, Unknown for a stray character), so the parser keeps
+    // its place and the mistake is reported once
+    pub fn next_token(&mut self) -> (SpannedToken, Option<Diagnostic>) {
+        if let Err(error) = self.skip_trivia() {
+            return (SpannedToken { token: Token::EOF, span: self.here() }, Some(error));
         }
+        self.token_start = self.here();
+        let first = self.peek_char();
+        let (token, error) = match self.lex_token() {
+            Ok(token) => (token, None),
+            Err(error) => {
+                let token = match first {
+                    Some('0'..='9' | '.') => {
+                        self.skip_number_rest();
+                        Token::IntLiteral { value: 0, radix: Radix::Decimal, suffix: IntSuffix::default() }
+                    }
+                    Some('\'') => Token::CharLiteral(0),
+                    Some('"') => Token::StringLiteral(Vec::new()),
+                    _ => Token::Unknown,
+                };
+                (token, Some(error))
+            }
+        };
+        (SpannedToken { token, span: Span { end: self.byte_pos, ..self.token_start } }, error)
     }
 
+    // what is left of a malformed number, like the suffix after a bad digit in 08u
+    fn skip_number_rest(&mut self) {
+        while self.peek_char().is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+            self.next_char();
+        }
+    }
 
-    pub fn next_token(&mut self) -> Token {
-        while let Some(ch) = self.next_char() {
+    fn lex_token(&mut self) -> Result<Token, Diagnostic> {
+        if let Some(ch) = self.next_char() {
             return match ch {
-                ' ' | '\t' | '\n' | '\r' => continue,
-                '+' => Token::Plus,
-                '-' => Token::Minus,
-                '*' => Token::Star,
-                '/' => Token::Slash,
-                '(' => Token::LParen,
-                ')' => Token::RParen,
-                '{' => Token::LBrace,
-                '}' => Token::RBrace,
-                ';' => Token::Semi,
-                ':' => Token::Colon,
-                '?' => Token::Question,
-                '~' => Token::bitwise,
-                '!' | '=' | '<' | '>' => self.lex_identifier_then(ch),
                 '0'..='9' => self.lex_number(ch),
-                'a'..='z' | 'A'..='Z' | '_' | '&' | '|' => self.lex_identifier(ch), // Identifiers (including keywords)
-                _ => panic!("Unexpected character: {}", ch),
+                '\'' => self.lex_char(),
+                '"' => self.lex_string(),
+                '.' if self.peek_char().is_some_and(|c| c.is_ascii_digit()) => {
+                    // ".5", the '.' is already eaten so put it back for lex_float
+                    self.pos -= 1;
+                    self.byte_pos -= 1;
+                    self.column -= 1;
+                    self.lex_float(String::new(), false)
+                }
+                'a'..='z' | 'A'..='Z' | '_' => Ok(self.lex_identifier(ch)), // Identifiers (including keywords)
+                _ => self.lex_punctuator(ch).ok_or_else(|| self.error("E0101", format!("Unexpected character: {}", ch))),
             };
         }
-        Token::EOF
+        Ok(Token::EOF)
     }
-}
\ No newline at end of file
+}
+
+
+fn radix_name(radix: Radix) -> &'static str {
+    match radix {
+        Radix::Decimal => "decimal",
+        Radix::Octal => "octal",
+        Radix::Hex => "hexadecimal",
+        Radix::Binary => "binary",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the first token of source and the code of its error, if it had one
+    fn lex(source: &str) -> (Token, Option<&'static str>) {
+        let (next, error) = Lexer::new(source).next_token();
+        (next.token, error.and_then(|error| error.code))
+    }
+
+    fn int(value: u64, radix: Radix, unsigned: bool, longs: u8) -> (Token, Option<&'static str>) {
+        (Token::IntLiteral { value, radix, suffix: IntSuffix { unsigned, longs } }, None)
+    }
+
+    #[test]
+    fn integer_radixes() {
+        assert_eq!(lex("42"), int(42, Radix::Decimal, false, 0));
+        assert_eq!(lex("0x1F"), int(31, Radix::Hex, false, 0));
+        assert_eq!(lex("017"), int(15, Radix::Octal, false, 0));
+        assert_eq!(lex("0b101"), int(5, Radix::Binary, false, 0));
+        assert_eq!(lex("0"), int(0, Radix::Octal, false, 0));
+        assert_eq!(lex("18446744073709551615"), int(u64::MAX, Radix::Decimal, false, 0));
+    }
+
+    #[test]
+    fn integer_suffixes() {
+        assert_eq!(lex("10u"), int(10, Radix::Decimal, true, 0));
+        assert_eq!(lex("42L"), int(42, Radix::Decimal, false, 1));
+        assert_eq!(lex("1uLL"), int(1, Radix::Decimal, true, 2));
+        assert_eq!(lex("1llu"), int(1, Radix::Decimal, true, 2));
+    }
+
+    #[test]
+    fn bad_integers_are_one_error_and_a_zero() {
+        let zero = Token::IntLiteral { value: 0, radix: Radix::Decimal, suffix: IntSuffix::default() };
+        assert_eq!(lex("10lL"), (zero.clone(), Some("E0108")));
+        assert_eq!(lex("1uu"), (zero.clone(), Some("E0108")));
+        assert_eq!(lex("08"), (zero.clone(), Some("E0106")));
+        assert_eq!(lex("0b12"), (zero.clone(), Some("E0106")));
+        assert_eq!(lex("0x"), (zero.clone(), Some("E0105")));
+        assert_eq!(lex("18446744073709551616"), (zero.clone(), Some("E0107")));
+
+        // the rest of the literal goes with it, the next token is what follows
+        let mut lexer = Lexer::new("08u;");
+        assert!(lexer.next_token().1.is_some());
+        let (next, error) = lexer.next_token();
+        assert_eq!((next.token, next.span.start, error.is_none()), (Token::Semi, 3, true));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(lex("1.5"), (Token::FloatLiteral { value: 1.5, suffix: FloatSuffix::None }, None));
+        assert_eq!(lex(".25f"), (Token::FloatLiteral { value: 0.25, suffix: FloatSuffix::F }, None));
+        assert_eq!(lex("1e3L"), (Token::FloatLiteral { value: 1000.0, suffix: FloatSuffix::L }, None));
+        assert_eq!(lex("0x1.8p1"), (Token::FloatLiteral { value: 3.0, suffix: FloatSuffix::None }, None));
+        assert_eq!(lex("1e999"), (Token::FloatLiteral { value: f64::INFINITY, suffix: FloatSuffix::None }, None));
+        assert_eq!(lex("1e").1, Some("E0109"));
+        assert_eq!(lex("0x1.8").1, Some("E0109"));
+        assert_eq!(lex("1.5q").1, Some("E0108"));
+    }
+
+    #[test]
+    fn a_stray_character_is_unknown() {
+        assert_eq!(lex("@"), (Token::Unknown, Some("E0101")));
+    }
+}
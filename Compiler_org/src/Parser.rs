@@ -1,37 +1,85 @@
-use std::collections::btree_map::VacantEntry;
-use std::fmt::format;
-use std::panic;
-use std::env;
-use std::process;
-use std::result;
-use std::thread::panicking;
 use crate::Lexer::Token;
 use crate::Lexer::Lexer;
+use crate::Lexer::Span;
+use crate::constant;
+use crate::diagnostics::Diagnostic;
+use crate::types::{self, CType, Radix, StructRef};
+use std::collections::HashMap;
 
 //this will be the output by the end
+//every node knows where it came from, so later stages can point at the source
 #[derive(Debug)]
-pub enum ASTNode {
+pub struct ASTNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub ty: Option<CType>, // the type of an expression, arrays not decayed yet. set by the semantic pass
+}
+
+#[derive(Debug)]
+#[allow(dead_code)] // not every node is generated yet
+pub enum NodeKind {
     //program
-    Program(Vec<ASTNode>),  // Holds a list of functions
+    Program(Vec<ASTNode>),  // Holds the functions and global variables of the file
 
     //function declaration
     Function {
         name: String,
-        body: Vec<ASTNode>,
-        return_type : String,
+        params: Vec<Param>,
+        variadic: bool, // ends in ...
+        prototyped: bool, // false for f(), which says nothing about the parameters in C89
+        body: Option<Vec<ASTNode>>, // None for a prototype: int f(int);
+        return_type : CType,
+        storage: Storage, // static functions aren't visible outside the file
     },
 
     //statement
-    Return(Box<ASTNode>),  // Holds an expression
+    Return(Option<Box<ASTNode>>),  // the value, None for a bare `return;`
 
-    Declare(String, Option<Box<ASTNode>>),  // Variable declaration (with optional initializer)
+    // Variable declaration (with optional initializer). at file scope, or with static/extern in a
+    // block, the variable lives in .data/.bss and the initializer has to be a constant
+    Declare {
+        name: String,
+        ty: CType,
+        storage: Storage,
+        read_only: bool, // declared const
+        init: Option<Box<ASTNode>>,
+    },
 
-    Assign(String, Box<ASTNode>),  // Variable assignment
+    Assign(Box<ASTNode>, Box<ASTNode>),  // lvalue = value, the lvalue is a variable or *pointer
+
+    CompoundAssign(Box<ASTNode>, BinaryOp, Box<ASTNode>),  // a += e, *p <<= e ... the lvalue is computed once
 
     Conditional(Box<ASTNode> ,  Vec<ASTNode> ,  Option<Vec<ASTNode>>), //exp is controlling condition
     //first statement is 'if' block
     //second statement is optional 'else' block
 
+    While(Box<ASTNode>, Vec<ASTNode>),  // condition, body
+
+    DoWhile(Vec<ASTNode>, Box<ASTNode>),  // body, condition
+
+    For {
+        init: Option<Box<ASTNode>>,  // a declaration or an expression statement
+        condition: Option<Box<ASTNode>>,  // no condition loops forever
+        step: Option<Box<ASTNode>>,
+        body: Vec<ASTNode>,
+    },
+
+    Block(Vec<ASTNode>),  // { ... } used as a statement, a scope of its own
+
+    DeclareList(Vec<ASTNode>),  // int a, *p = &a; one Declare per declarator, in the scope the list is in
+
+    Switch(Box<ASTNode>, Vec<ASTNode>),  // controlling expression, body
+
+    // case value: statement and default: statement, anywhere in the body of a switch.
+    // their span is the label alone, the statement has its own
+    Case(Box<ASTNode>, Box<ASTNode>),
+
+    Default(Box<ASTNode>),
+
+    Break,
+
+    Continue,
+
 
 
 
@@ -42,11 +90,43 @@ pub enum ASTNode {
 
     Var(String),  // Represents variable access
 
+    Call(String, Vec<ASTNode>),  // function name, arguments
+
     BinaryOp(Box<ASTNode>, BinaryOp, Box<ASTNode>),  // Binary operation
 
     UnaryOp(UnaryOp, Box<ASTNode>),  // Unary operation
 
-    Constant(i64),  // Integer constants
+    Constant(i64, CType),  // Integer constants, the value is the bit pattern of the literal's type
+
+    FloatConstant(f64, CType),  // float and double constants
+
+    StringLiteral(Vec<u8>),  // "text", adjacent literals already joined, no terminating 0
+
+    Cast(CType, Box<ASTNode>),  // (type) expression, also used for conversions the language does implicitly
+
+    InitList(Vec<ASTNode>),  // { 1, 2, { 3 } } initializing an array or struct, only found in a Declare
+
+    Member(Box<ASTNode>, String),  // s.x, and p->x as (*p).x
+
+    Empty,  // a declaration that only introduces a type: struct point { int x; int y; };
+}
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Storage {
+    Default, // nothing written: external linkage at file scope, on the stack in a block
+    Static,
+    Extern,
+}
+
+
+
+#[derive(Debug)]
+pub struct Param {
+    pub name: Option<String>, // a prototype may leave the names out
+    pub ty: CType,
+    pub span: Span,
 }
 
 
@@ -56,6 +136,13 @@ pub enum UnaryOp {
     Not,    // "!"
     BitNot, // "~"
     Negate, // "-"
+    Plus,   // "+"
+    PreIncrement,  // "++a"
+    PreDecrement,  // "--a"
+    PostIncrement, // "a++"
+    PostDecrement, // "a--"
+    AddressOf,     // "&a"
+    Deref,         // "*p"
 }
 
 
@@ -74,110 +161,814 @@ pub enum BinaryOp{
     Equal,
     LogAnd,
     LogOr,
+    Modulo,
+    ShiftLeft,
+    ShiftRight,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Comma,
+}
+
+
+
+// after this many errors we stop, whatever comes next is most likely noise
+const MAX_ERRORS: usize = 20;
+
+// what a tag stands for. struct, union and enum tags share one namespace
+#[derive(Clone)]
+enum Tag {
+    Aggregate(StructRef),
+    Enum,
 }
 
+// the ordinary identifiers of a block that the parser has to know about. an enumerator is
+// replaced by its value where it is used and a typedef name is read as a type specifier,
+// everything else is left to the semantic pass
+#[derive(Clone)]
+enum Name {
+    Object, // a variable, a parameter or a function
+    Enumerator(i64),
+    Typedef(CType, bool), // the type and whether it is const
+}
 
+// what comes before the declarators: `static const struct point`
+struct Specifiers {
+    storage: Storage,
+    read_only: bool,
+    ty: CType,
+    tagged: bool, // a struct, union or enum specifier, which may be declared with no declarator
+    typedef: bool, // the declarators are type names, not objects
+}
 
 pub struct Parser {
     lexer: Lexer,
     current_token: Token,
+    current_span: Span,
+    prev_span: Span, // span of the last token we ate, that's where a node ends
+    diagnostics: Vec<Diagnostic>, // every error we recovered from
+    gave_up: bool,
+    loop_depth: usize, // how many loops we are in, break and continue need at least one
+    switch_depth: usize, // and how many switches, break and case need one of those
+    tags: Vec<HashMap<String, Tag>>, // struct, union and enum tags of each block we are in, file scope first
+    names: Vec<HashMap<String, Name>>, // and the ordinary identifiers, in the same order
 }
 
 impl Parser
 {
     pub fn new(lexer: Lexer) -> Self {
-        let mut parser = Self { current_token : Token::EOF, lexer };
-        parser.current_token = parser.lexer.next_token();
+        let mut parser = Self {
+            current_token : Token::EOF,
+            current_span: Span::default(),
+            prev_span: Span::default(),
+            lexer,
+            diagnostics: Vec::new(),
+            gave_up: false,
+            loop_depth: 0,
+            switch_depth: 0,
+            tags: vec![HashMap::new()],
+            names: vec![HashMap::new()],
+        };
+        parser.advance();
         parser
     }
 
-    pub fn eat(&mut self, expected: Token) {
+    //moves to the next token. a lexer error is recorded and we go on with the token the lexer
+    //put in its place, an error of ours on that same token is then dropped as a cascade
+    fn advance(&mut self) {
+        self.prev_span = self.current_span;
+        let (next, error) = self.lexer.next_token();
+        if let Some(error) = error {
+            self.report(error);
+            if next.token == Token::EOF {
+                //the error ate the rest of the file (an unterminated comment), whatever is missing
+                //now was cut off by it, so stop like after too many errors
+                self.current_token = Token::EOF;
+                self.current_span = next.span;
+                self.gave_up = true;
+            }
+        }
+        if !self.gave_up {
+            self.current_token = next.token;
+            self.current_span = next.span;
+        }
+    }
+
+    pub fn eat(&mut self, expected: Token) -> Result<(), Diagnostic> {
         if self.current_token == expected {
-            self.current_token = self.lexer.next_token();
+            self.advance();
+            Ok(())
         } else {
-            panic!("Unexpected token: {:?}, expected: {:?}", self.current_token, expected);
+            let mut error = Diagnostic::error(format!("Unexpected token: {:?}, expected: {:?}", self.current_token, expected), self.current_span)
+                .with_code("E0201");
+            if expected == Token::Semi {
+                //the semicolon belongs right after the previous token, not where we noticed it missing
+                error = error.with_fix(self.prev_span.after(), ";", "add a ';' here");
+            }
+            Err(error)
+        }
+    }
+
+    //error pointing at the token we are standing on
+    fn error_here(&self, code: &'static str, message: &str) -> Diagnostic {
+        Diagnostic::error(format!("{}, found {:?}", message, self.current_token), self.current_span).with_code(code)
+    }
+
+    //keeps an error and goes on. an error on the same token as the last one is a cascade, so it is dropped
+    fn report(&mut self, error: Diagnostic) {
+        if self.gave_up {
+            return;
+        }
+        if let Some(last) = self.diagnostics.last() {
+            if last.span.start == error.span.start {
+                return;
+            }
+        }
+        self.diagnostics.push(error);
+        if self.diagnostics.len() >= MAX_ERRORS {
+            let span = self.current_span;
+            self.diagnostics.push(Diagnostic::error("too many errors, stopping", span).with_code("E0207"));
+            //pretend the file ended here so every loop unwinds
+            self.current_token = Token::EOF;
+            self.gave_up = true;
+        }
+    }
+
+    //panic mode inside a function body: skip to the end of the broken statement.
+    //a ';' is eaten, a '}' is left for whoever owns the block
+    fn synchronize_statement(&mut self) {
+        loop {
+            match self.current_token {
+                Token::Semi => {
+                    self.advance();
+                    return;
+                }
+                Token::RBrace | Token::EOF => return,
+                _ => self.advance(),
+            }
+        }
+    }
+
+    //panic mode at file scope: skip what is left of the broken function (braces balanced)
+    //or stop at the next type keyword that could start a new declaration
+    fn synchronize_top_level(&mut self) {
+        let mut depth = 0;
+        let mut first = true;
+        loop {
+            match &self.current_token {
+                Token::EOF => return,
+                Token::Keyword(keyword) if depth == 0 && !first && is_declaration_keyword(keyword) => return,
+                Token::LBrace => depth += 1,
+                Token::RBrace => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                Token::Semi if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                _ => {}
+            }
+            self.advance();
+            first = false;
         }
     }
 
+    //builds a node that starts at `start` and ends with the last token we ate
+    fn node(&self, kind: NodeKind, start: Span) -> ASTNode {
+        ASTNode { kind, span: start.to(self.prev_span), ty: None }
+    }
+
 
     //changing parse to promgram parsing. cause we had parsing function as main, later on we will have functions so that will mess this up.
-    pub fn parse(&mut self) -> ASTNode {
+    //syntax errors don't stop us, we resync and keep going so all of them are reported in one run
+    pub fn parse(&mut self) -> Result<ASTNode, Vec<Diagnostic>> {
+        let start = self.current_span;
         let mut functions = Vec::new();
         while self.current_token != Token::EOF {
-            functions.push(self.parse_function());
+            match self.parse_top_level() {
+                Ok(function) => functions.push(function),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize_top_level();
+                }
+            }
+        }
+        if self.diagnostics.is_empty() {
+            Ok(self.node(NodeKind::Program(functions), start))
+        } else {
+            //the lexer runs a token ahead of us, put its errors back in source order
+            let mut diagnostics = std::mem::take(&mut self.diagnostics);
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            Err(diagnostics)
         }
-        ASTNode::Program(functions)
     }
 
-    // Function parsing: `int main() { return 100; }`
-    fn parse_function(&mut self) -> ASTNode {
-        let return_type = self.parse_type();
+    // a function or a global variable
+    fn parse_top_level(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        let specifiers = self.parse_specifiers()?;
+        if let Some(empty) = self.parse_tag_only(start, specifiers.tagged)? {
+            return Ok(empty);
+        }
+        if specifiers.typedef {
+            return self.parse_typedef(start, specifiers.read_only, specifiers.ty);
+        }
+        let (ty, read_only) = self.parse_pointers(specifiers.ty.clone(), specifiers.read_only);
         let name = if let Token::Ident(name) = self.current_token.clone() {
-            self.eat(Token::Ident(name.clone()));
+            self.eat(Token::Ident(name.clone()))?;
             name
         } else {
-            panic!("Expected function name");
+            return Err(self.error_here("E0202", "Expected a function or variable name"));
         };
+        if self.current_token == Token::LParen {
+            self.parse_function(start, specifiers.storage, ty, name)
+        } else {
+            self.parse_declarators(start, &specifiers, ty, read_only, name)
+        }
+    }
+
+    // Function parsing: `int main() { return 100; }` or a prototype `int f(int);`
+    // everything up to the name is already parsed
+    fn parse_function(&mut self, start: Span, storage: Storage, return_type: CType, name: String) -> Result<ASTNode, Diagnostic> {
+        self.declare_name(&name, Name::Object, self.prev_span)?;
+        let (params, variadic, prototyped) = self.parse_params()?;
+
+        if self.current_token == Token::Semi {
+            self.eat(Token::Semi)?;
+            return Ok(self.node(NodeKind::Function { name, params, variadic, prototyped, return_type, storage, body: None }, start));
+        }
+        if let Some(unnamed) = params.iter().find(|param| param.name.is_none()) {
+            return Err(Diagnostic::error("parameter name omitted", unnamed.span).with_code("E0213"));
+        }
+        let body = self.parse_scope(&params)?;
+
+        Ok(self.node(NodeKind::Function { name, params, variadic, prototyped, return_type, storage, body: Some(body) }, start))
+    }
 
-        self.eat(Token::LParen);
-        self.eat(Token::RParen);
-        self.eat(Token::LBrace);
+    // (int a, double b, ...), and whether there was a parameter list at all: () isn't (void) in C89
+    fn parse_params(&mut self) -> Result<(Vec<Param>, bool, bool), Diagnostic> {
+        self.eat(Token::LParen)?;
+        if self.current_token == Token::RParen {
+            self.eat(Token::RParen)?;
+            return Ok((Vec::new(), false, false));
+        }
+        let mut params = Vec::new();
+        while self.current_token != Token::RParen {
+            if !params.is_empty() {
+                self.eat(Token::Comma)?;
+            }
+            if self.current_token == Token::Ellipsis {
+                if params.is_empty() {
+                    return Err(self.error_here("E0212", "a named parameter is required before '...'"));
+                }
+                self.eat(Token::Ellipsis)?;
+                self.eat(Token::RParen)?;
+                return Ok((params, true, true));
+            }
+            let start = self.current_span;
+            let ty = self.parse_type()?;
+            // (void) alone, but void *p is a parameter
+            if params.is_empty() && ty == CType::Void && self.current_token == Token::RParen {
+                self.eat(Token::RParen)?;
+                return Ok((params, false, true));
+            }
+            let (ty, _) = self.parse_pointers(ty, false);
+            let name = if let Token::Ident(name) = self.current_token.clone() {
+                self.eat(Token::Ident(name.clone()))?;
+                Some(name)
+            } else {
+                None
+            };
+            // an array parameter is really a pointer, int a[] and int *a mean the same
+            let ty = self.parse_array_suffix(ty, name.as_deref().unwrap_or("parameter"))?.decay();
+            params.push(Param { name, ty, span: start.to(self.prev_span) });
+        }
+        self.eat(Token::RParen)?;
+        Ok((params, false, true))
+    }
 
+    // { declarations and statements }. a broken item is reported and skipped, the rest still parses
+    fn parse_block(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
+        self.parse_scope(&[])
+    }
 
+    // a block that also declares the parameters of a function, they are in the same scope
+    // as its outermost declarations
+    fn parse_scope(&mut self, params: &[Param]) -> Result<Vec<ASTNode>, Diagnostic> {
+        self.eat(Token::LBrace)?;
+        self.enter_scope();
+        for param in params {
+            if let Some(name) = &param.name {
+                self.declare_name(name, Name::Object, param.span)?;
+            }
+        }
         let mut body = Vec::new();
-        while self.current_token != Token::RBrace {
-            if let Token::Keyword(keyword) = &self.current_token {
-                if keyword == "int" {
-                body.push(self.parse_Assign_Or_declare()); // Declaration
+        while self.current_token != Token::RBrace && self.current_token != Token::EOF {
+            // a lone ';' is an empty statement
+            if self.current_token == Token::Semi {
+                self.advance();
                 continue;
-                
+            }
+            let item = match &self.current_token {
+                _ if self.at_declaration() => self.parse_Assign_Or_declare(), // Declaration
+                _ => self.parse_statement(), // Statements
+            };
+            match item {
+                Ok(item) => body.push(item),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize_statement();
                 }
             }
+        }
+        self.leave_scope();
+        self.eat(Token::RBrace)?;
+        Ok(body)
+    }
 
-            body.push(self.parse_statement()); // Statements
+    fn enter_scope(&mut self) {
+        self.tags.push(HashMap::new());
+        self.names.push(HashMap::new());
+    }
+
+    fn leave_scope(&mut self) {
+        self.tags.pop();
+        self.names.pop();
+    }
+
+    // puts an ordinary identifier in the innermost scope. a name can only be declared again in
+    // the same scope as the same kind of thing: a typedef with the same type, or a variable, which
+    // the semantic pass checks further. an enumerator can't be declared twice at all
+    fn declare_name(&mut self, name: &str, kind: Name, span: Span) -> Result<(), Diagnostic> {
+        let scope = self.names.last_mut().expect("there is always file scope");
+        match (scope.get(name), &kind) {
+            (None, _) | (Some(Name::Object), Name::Object) => {
+                scope.insert(name.to_string(), kind);
+                Ok(())
+            }
+            (Some(Name::Typedef(old, old_read_only)), Name::Typedef(new, new_read_only)) => {
+                if old == new && old_read_only == new_read_only {
+                    Ok(())
+                } else {
+                    Err(Diagnostic::error(format!("conflicting types for typedef '{}' ('{}' and '{}')", name, old, new), span)
+                        .with_code("E0222"))
+                }
+            }
+            (Some(Name::Enumerator(_)), Name::Enumerator(_)) => {
+                Err(Diagnostic::error(format!("redeclaration of enumerator '{}'", name), span).with_code("E0222"))
+            }
+            _ => Err(Diagnostic::error(format!("'{}' redeclared as different kind of symbol", name), span).with_code("E0222")),
         }
+    }
 
-        self.eat(Token::RBrace);
+    // the value of `name` if the declaration it refers to is an enumerator
+    fn enumerator(&self, name: &str) -> Option<i64> {
+        match self.names.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(Name::Enumerator(value)) => Some(*value),
+            _ => None,
+        }
+    }
 
-        ASTNode::Function { name, return_type ,body }
+    // the type `name` stands for if it is a typedef name here, and whether that type is const.
+    // a variable in an inner block hides the typedef, `T * x` is then a multiplication
+    fn type_name(&self, name: &str) -> Option<(CType, bool)> {
+        match self.names.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(Name::Typedef(ty, read_only)) => Some((ty.clone(), *read_only)),
+            _ => None,
+        }
     }
 
+    // the body of an if or a loop: a block, a single statement or just ';'
+    fn parse_body(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
+        if self.current_token == Token::LBrace {
+            self.parse_block()
+        } else if self.current_token == Token::Semi {
+            self.eat(Token::Semi)?;
+            Ok(Vec::new())
+        } else {
+            Ok(vec![self.parse_statement()?])
+        }
+    }
 
-    fn parse_type(&mut self) ->String{
-        match self.current_token.clone() {
-            Token::Keyword(keyword) if keyword == "int" =>{
-                self.eat(Token::Keyword("int".to_string()));
-                return "int".to_string();
+
+    // static/extern/typedef, const and the type keywords, in any order: `unsigned long const static x`.
+    // a typedef name is a type specifier too, as long as no other one came before it
+    fn parse_specifiers(&mut self) -> Result<Specifiers, Diagnostic> {
+        let start = self.current_span;
+        let mut storage = Storage::Default;
+        let mut typedef = false;
+        let mut read_only = false;
+        let mut words = Vec::new();
+        let mut named = None; // the type of a struct, union or enum specifier or of a typedef name
+        let mut tagged = false;
+        loop {
+            let specifier = match &self.current_token {
+                Token::Keyword(keyword) if keyword == "static" => Some(Storage::Static),
+                Token::Keyword(keyword) if keyword == "extern" => Some(Storage::Extern),
+                Token::Keyword(keyword) if keyword == "typedef" => None,
+                Token::Keyword(keyword) if keyword == "const" => {
+                    read_only = true;
+                    self.eat(self.current_token.clone())?;
+                    continue;
+                }
+                Token::Keyword(keyword) if keyword == "struct" || keyword == "union" || keyword == "enum" => {
+                    if named.is_some() || !words.is_empty() {
+                        return Err(self.error_here("E0221", "two or more data types in declaration specifiers"));
+                    }
+                    let ty = if keyword == "enum" { self.parse_enum_specifier()? } else { self.parse_struct_specifier()? };
+                    named = Some(ty);
+                    tagged = true;
+                    continue;
+                }
+                Token::Keyword(keyword) if is_type_keyword(keyword) => {
+                    if named.is_some() {
+                        return Err(self.error_here("E0221", "two or more data types in declaration specifiers"));
+                    }
+                    words.push(keyword.clone());
+                    self.eat(self.current_token.clone())?;
+                    continue;
+                }
+                // after a type specifier an identifier is the declarator, even one that names a type
+                Token::Ident(name) if named.is_none() && words.is_empty() => {
+                    let Some((ty, named_read_only)) = self.type_name(name) else {
+                        break;
+                    };
+                    read_only |= named_read_only;
+                    named = Some(ty);
+                    self.eat(self.current_token.clone())?;
+                    continue;
+                }
+                _ => break,
+            };
+            if storage != Storage::Default || typedef {
+                return Err(self.error_here("E0214", "multiple storage classes in declaration specifiers"));
+            }
+            match specifier {
+                Some(specifier) => storage = specifier,
+                None => typedef = true,
+            }
+            self.eat(self.current_token.clone())?;
+        }
+        let ty = match named {
+            Some(ty) => ty,
+            None if words.is_empty() => return Err(self.error_here("E0203", "Expected a type name")),
+            None => type_from_words(&words).ok_or_else(|| {
+                Diagnostic::error(format!("invalid combination of type specifiers '{}'", words.join(" ")), start.to(self.prev_span))
+                    .with_code("E0221")
+            })?,
+        };
+        Ok(Specifiers { storage, read_only, ty, tagged, typedef })
+    }
+
+    // the type of a parameter, a member or a cast: specifiers without a storage class
+    fn parse_type(&mut self) -> Result<CType, Diagnostic> {
+        let start = self.current_span;
+        let Specifiers { storage, ty, typedef, .. } = self.parse_specifiers()?;
+        if storage != Storage::Default || typedef {
+            return Err(Diagnostic::error("storage class specified for a type name", start.to(self.prev_span)).with_code("E0214"));
+        }
+        Ok(ty)
+    }
+
+    // struct tag, struct tag { members } or struct { members }. a tag is looked up from the
+    // innermost block out; `struct tag;` and a definition always declare it in the current block
+    fn parse_struct_specifier(&mut self) -> Result<CType, Diagnostic> {
+        let start = self.current_span;
+        let union = self.current_token == Token::Keyword("union".to_string());
+        let keyword = if union { "union" } else { "struct" };
+        self.eat(self.current_token.clone())?;
+        let tag = if let Token::Ident(tag) = self.current_token.clone() {
+            self.eat(Token::Ident(tag.clone()))?;
+            Some(tag)
+        } else {
+            None
+        };
+        let defining = self.current_token == Token::LBrace;
+
+        let def = match &tag {
+            None if !defining => return Err(self.error_here("E0205", &format!("Expected a tag or '{{' after '{}'", keyword))),
+            None => StructRef::declare(union, None),
+            Some(tag) => {
+                let own_block = defining || self.current_token == Token::Semi;
+                let found = if own_block {
+                    self.tags.last().and_then(|scope| scope.get(tag))
+                } else {
+                    self.tags.iter().rev().find_map(|scope| scope.get(tag))
+                };
+                match found {
+                    Some(Tag::Enum) => {
+                        return Err(Diagnostic::error(format!("'{}' defined as wrong kind of tag", tag), start.to(self.prev_span)).with_code("E0218"));
+                    }
+                    Some(Tag::Aggregate(def)) if def.is_union() != union => {
+                        return Err(Diagnostic::error(format!("'{}' defined as wrong kind of tag", tag), start.to(self.prev_span)).with_code("E0218"));
+                    }
+                    Some(Tag::Aggregate(def)) if defining && def.is_complete() => {
+                        let error = Diagnostic::error(format!("redefinition of '{} {}'", keyword, tag), start.to(self.prev_span)).with_code("E0217");
+                        self.parse_members(keyword)?; // still read to the '}', so the next error isn't a cascade of this one
+                        return Err(error);
+                    }
+                    Some(Tag::Aggregate(def)) => def.clone(),
+                    None => {
+                        let def = StructRef::declare(union, Some(tag.clone()));
+                        self.tags.last_mut().expect("there is always file scope").insert(tag.clone(), Tag::Aggregate(def.clone()));
+                        def
+                    }
+                }
+            }
+        };
+        if defining {
+            let members = self.parse_members(keyword)?;
+            def.define(members);
+        }
+        Ok(CType::Struct(def))
+    }
+
+    // enum tag, enum tag { A, B = 5, C } or enum { ... }. an enum is an int, and its
+    // enumerators are int constants declared in the current block
+    fn parse_enum_specifier(&mut self) -> Result<CType, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("enum".to_string()))?;
+        let tag = if let Token::Ident(tag) = self.current_token.clone() {
+            self.eat(Token::Ident(tag.clone()))?;
+            Some(tag)
+        } else {
+            None
+        };
+        let defining = self.current_token == Token::LBrace;
+
+        if let Some(tag) = &tag {
+            let found = if defining {
+                self.tags.last().and_then(|scope| scope.get(tag))
+            } else {
+                self.tags.iter().rev().find_map(|scope| scope.get(tag))
+            };
+            match found {
+                Some(Tag::Aggregate(_)) => {
+                    let error = Diagnostic::error(format!("'{}' defined as wrong kind of tag", tag), start.to(self.prev_span)).with_code("E0218");
+                    return Err(self.skip_enumerators(defining, error));
+                }
+                Some(Tag::Enum) if defining => {
+                    let error = Diagnostic::error(format!("redefinition of 'enum {}'", tag), start.to(self.prev_span)).with_code("E0217");
+                    return Err(self.skip_enumerators(defining, error));
+                }
+                Some(Tag::Enum) => {}
+                None if defining => {
+                    self.tags.last_mut().expect("there is always file scope").insert(tag.clone(), Tag::Enum);
+                }
+                None => {
+                    return Err(Diagnostic::error(format!("use of undefined 'enum {}'", tag), start.to(self.prev_span)).with_code("E0224"));
+                }
+            }
+        } else if !defining {
+            return Err(self.error_here("E0205", "Expected a tag or '{' after 'enum'"));
+        }
+
+        if defining {
+            self.eat(Token::LBrace)?;
+            if let Err(error) = self.parse_enumerators() {
+                self.skip_braces();
+                return Err(error);
+            }
+            self.eat(Token::RBrace)?;
+        }
+        Ok(CType::Int)
+    }
+
+    // the { } of an enum we already gave up on. its enumerators would only be reported again
+    fn skip_enumerators(&mut self, defining: bool, error: Diagnostic) -> Diagnostic {
+        if defining {
+            self.advance();
+            self.skip_braces();
+        }
+        error
+    }
+
+    // A, B = A + 2, C, up to the '}'. an enumerator without a value is one more than the
+    // one before it, the first one is 0
+    fn parse_enumerators(&mut self) -> Result<(), Diagnostic> {
+        let mut next = 0;
+        loop {
+            let name_span = self.current_span;
+            let Token::Ident(name) = self.current_token.clone() else {
+                return Err(self.error_here("E0205", "Expected an enumerator name"));
+            };
+            self.eat(Token::Ident(name.clone()))?;
+            let value = if self.current_token == Token::Assign {
+                self.eat(Token::Assign)?;
+                let value = self.parse_Conditions()?;
+                match constant::eval(&value) {
+                    Some((constant::Value::Int(value), _)) => value,
+                    _ => {
+                        return Err(Diagnostic::error(format!("enumerator value for '{}' is not an integer constant", name), value.span)
+                            .with_code("E0223"));
+                    }
+                }
+            } else {
+                next
+            };
+            if i32::try_from(value).is_err() {
+                return Err(Diagnostic::error(format!("enumerator value for '{}' is out of the range of 'int'", name), name_span.to(self.prev_span))
+                    .with_code("E0223"));
+            }
+            self.declare_name(&name, Name::Enumerator(value), name_span)?;
+            next = value + 1;
+            if self.current_token != Token::Comma {
+                return Ok(());
+            }
+            self.eat(Token::Comma)?;
+            // a trailing comma is allowed
+            if self.current_token == Token::RBrace {
+                return Ok(());
+            }
+        }
+    }
+
+    // { int x; double a[2], *p; } the member declarations of a struct or union.
+    // after an error the rest of the braces is skipped
+    fn parse_members(&mut self, keyword: &str) -> Result<Vec<(String, CType)>, Diagnostic> {
+        self.eat(Token::LBrace)?;
+        let members = self.parse_member_declarations(keyword);
+        if members.is_ok() {
+            self.eat(Token::RBrace)?;
+        } else {
+            self.skip_braces();
+        }
+        members
+    }
+
+    // after an error inside { }, skips past the '}' that closes them
+    fn skip_braces(&mut self) {
+        let mut depth = 1;
+        while depth > 0 && self.current_token != Token::EOF {
+            match self.current_token {
+                Token::LBrace => depth += 1,
+                Token::RBrace => depth -= 1,
+                _ => {}
             }
-            Token::Keyword(keyword) if keyword== "void" =>{
-                self.eat(Token::Keyword("void".to_string()));
-                return "void".to_string();
+            self.advance();
+        }
+    }
+
+    fn parse_member_declarations(&mut self, keyword: &str) -> Result<Vec<(String, CType)>, Diagnostic> {
+        let mut members: Vec<(String, CType)> = Vec::new();
+        while self.current_token != Token::RBrace {
+            let start = self.current_span;
+            let Specifiers { storage, ty: base, typedef, .. } = self.parse_specifiers()?;
+            if storage != Storage::Default || typedef {
+                return Err(Diagnostic::error(format!("storage class in a {} member", keyword), start).with_code("E0214"));
+            }
+            loop {
+                let (ty, _) = self.parse_pointers(base.clone(), false);
+                let name_span = self.current_span;
+                let Token::Ident(name) = self.current_token.clone() else {
+                    return Err(self.error_here("E0205", "Expected a member name"));
+                };
+                self.eat(Token::Ident(name.clone()))?;
+                let ty = self.parse_array_suffix(ty, &name)?;
+                let incomplete = match &ty {
+                    CType::Struct(def) => !def.is_complete(),
+                    CType::Void | CType::Array(_, 0) => true,
+                    _ => false,
+                };
+                if incomplete {
+                    return Err(Diagnostic::error(format!("field '{}' has incomplete type '{}'", name, ty), name_span).with_code("E0219"));
+                }
+                if members.iter().any(|(other, _)| *other == name) {
+                    return Err(Diagnostic::error(format!("duplicate member '{}'", name), name_span).with_code("E0220"));
+                }
+                members.push((name, ty));
+                if self.current_token != Token::Comma {
+                    break;
+                }
+                self.eat(Token::Comma)?;
             }
-            _ => panic!("Expected type keyword , supporting int and void"),
+            self.eat(Token::Semi)?;
         }
+        Ok(members)
     }
 
+    // `struct point { ... };` declares the type and no variable, so does `enum { A, B };`
+    fn parse_tag_only(&mut self, start: Span, tagged: bool) -> Result<Option<ASTNode>, Diagnostic> {
+        if !tagged || self.current_token != Token::Semi {
+            return Ok(None);
+        }
+        self.eat(Token::Semi)?;
+        Ok(Some(self.node(NodeKind::Empty, start)))
+    }
 
-    fn parse_unused_expression(&mut self) -> ASTNode {
-        let left = self.parse_expression().unwrap();
-        match &left {  
-            _ => {
-                self.eat(Token::Semi);
-                ASTNode::Exp(Box::new(left))  
+    // the '*'s in front of a declarator: int **p is a pointer to a pointer to int
+    // the *s of a declarator, each with its own const: in `const char *const p` the first const
+    // is about the chars and the second about p. gives back the type and whether the declared
+    // thing itself is const, which with no * is up to the specifiers
+    fn parse_pointers(&mut self, mut ty: CType, mut read_only: bool) -> (CType, bool) {
+        while self.current_token == Token::Star {
+            self.advance();
+            ty = ty.pointer_to();
+            read_only = false;
+            while matches!(&self.current_token, Token::Keyword(keyword) if keyword == "const") {
+                self.advance();
+                read_only = true;
             }
         }
+        (ty, read_only)
     }
 
+    // the [N] after a declarator name. int a[2][3] is an array of 2 arrays of 3 ints, so the
+    // last size is the innermost one. the first size may be left out: int a[] = {1, 2}
+    fn parse_array_suffix(&mut self, ty: CType, name: &str) -> Result<CType, Diagnostic> {
+        let mut counts = Vec::new();
+        while self.current_token == Token::LBracket {
+            self.eat(Token::LBracket)?;
+            if self.current_token == Token::RBracket && counts.is_empty() {
+                self.eat(Token::RBracket)?;
+                counts.push(0);
+                continue;
+            }
+            let size = self.parse_Conditions()?;
+            let count = match constant::eval(&size) {
+                Some((constant::Value::Int(count), ty)) if count > 0 || (!ty.is_signed() && count != 0) => count as u64,
+                Some((constant::Value::Int(_), _)) => {
+                    return Err(Diagnostic::error(format!("size of array '{}' is not positive", name), size.span).with_code("E0216"));
+                }
+                _ => return Err(Diagnostic::error(format!("size of array '{}' is not an integer constant", name), size.span).with_code("E0215")),
+            };
+            self.eat(Token::RBracket)?;
+            counts.push(count);
+        }
+        Ok(counts.into_iter().rev().fold(ty, |element, count| CType::Array(Box::new(element), count)))
+    }
 
-    fn parse_statement(&mut self) -> ASTNode {
+    // what comes after '=' in a declaration: an expression or a { } list, which can nest
+    fn parse_initializer(&mut self) -> Result<ASTNode, Diagnostic> {
+        if self.current_token != Token::LBrace {
+            return self.parse_assignment(); // a ',' would end the declarator
+        }
+        let start = self.current_span;
+        self.eat(Token::LBrace)?;
+        let mut items = Vec::new();
+        while self.current_token != Token::RBrace {
+            items.push(self.parse_initializer()?);
+            // the last item may have a ',' after it
+            if self.current_token != Token::RBrace {
+                self.eat(Token::Comma)?;
+            }
+        }
+        self.eat(Token::RBrace)?;
+        Ok(self.node(NodeKind::InitList(items), start))
+    }
+
+    // whether a type name starts here: (int) x is a cast, (x) is not
+    fn at_type_name(&self) -> bool {
+        match &self.current_token {
+            Token::Keyword(keyword) => is_type_keyword(keyword) || keyword == "const",
+            Token::Ident(name) => self.type_name(name).is_some(),
+            _ => false,
+        }
+    }
+
+    // whether a declaration starts here rather than a statement
+    fn at_declaration(&self) -> bool {
+        match &self.current_token {
+            Token::Keyword(keyword) => is_declaration_keyword(keyword),
+            _ => self.at_type_name(),
+        }
+    }
+
+
+    fn parse_unused_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        let left = self.parse_expression()?;
+        self.eat(Token::Semi)?;
+        Ok(self.node(NodeKind::Exp(Box::new(left)), start))
+    }
+
+
+    fn parse_statement(&mut self) -> Result<ASTNode, Diagnostic> {
         
         match self.current_token.clone() {
             Token::Keyword(keyword) if keyword == "return" => self.parse_return(),
-            Token::Keyword(keyword) if keyword == "int" => self.parse_Assign_Or_declare(), // Handle declaration
-            Token::Number(value) => self.parse_unused_expression(),
-            Token::Ident(_) => self.parse_assignment_or_expression(), // Handle variable assignment
+            Token::Keyword(keyword) if keyword == "if" => self.parse_if(),
+            Token::LBrace => {
+                let start = self.current_span;
+                let body = self.parse_block()?;
+                Ok(self.node(NodeKind::Block(body), start))
+            }
+            Token::Keyword(keyword) if keyword == "while" => self.parse_while(),
+            Token::Keyword(keyword) if keyword == "do" => self.parse_do_while(),
+            Token::Keyword(keyword) if keyword == "for" => self.parse_for(),
+            Token::Keyword(keyword) if keyword == "switch" => self.parse_switch(),
+            Token::Keyword(keyword) if keyword == "case" || keyword == "default" => self.parse_case(&keyword),
+            Token::Keyword(keyword) if keyword == "break" || keyword == "continue" => self.parse_jump(&keyword),
+            _ if self.at_declaration() => self.parse_Assign_Or_declare(), // Handle declaration
+            Token::IntLiteral { .. } | Token::FloatLiteral { .. } | Token::CharLiteral(_) | Token::StringLiteral(_)
+            | Token::Ident(_) | Token::LParen | Token::Minus | Token::Plus | Token::bitwise | Token::logical
+            | Token::Increment | Token::Decrement | Token::Star | Token::Ampersand => self.parse_unused_expression(),
             _ =>{
-                panic!("Unexpected statement")
+                Err(self.error_here("E0204", "Unexpected statement"))
             } ,
         }
     }
@@ -187,79 +978,142 @@ impl Parser
     
     //All the parse expressions go here : 
     
-    fn parse_expression(&mut self) -> Option<ASTNode> {
-        // Check if it's a variable declaration (e.g., int a;)
-        if let Token::Keyword(keyword) = self.current_token.clone() {
-            if keyword == "int" {
-                return Some(self.parse_Assign_Or_declare());
-            }
-        }
-        if let Token::Ident(_) = self.current_token.clone(){
-            return Some(self.parse_assignment_or_expression());
+    // a full expression, commas included: a = 1, b = 2
+    fn parse_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_assignment()?;
+        while self.current_token == Token::Comma {
+            self.eat(Token::Comma)?;
+            let right = self.parse_assignment()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), BinaryOp::Comma, Box::new(right)), span, ty: None };
         }
+        Ok(left)
+    }
 
-
-        self.parse_Conditions() // Return whatever expression was parsed
+    // assignments are right associative: a = b += 2 is a = (b += 2)
+    fn parse_assignment(&mut self) -> Result<ASTNode, Diagnostic> {
+        let left = self.parse_Conditions()?;
+        let op = match self.current_token {
+            Token::Assign => None,
+            Token::PlusAssign => Some(BinaryOp::Addition),
+            Token::MinusAssign => Some(BinaryOp::Subtraction),
+            Token::StarAssign => Some(BinaryOp::Multiplication),
+            Token::SlashAssign => Some(BinaryOp::Division),
+            Token::PercentAssign => Some(BinaryOp::Modulo),
+            Token::AndAssign => Some(BinaryOp::BitAnd),
+            Token::OrAssign => Some(BinaryOp::BitOr),
+            Token::XorAssign => Some(BinaryOp::BitXor),
+            Token::ShiftLeftAssign => Some(BinaryOp::ShiftLeft),
+            Token::ShiftRightAssign => Some(BinaryOp::ShiftRight),
+            _ => return Ok(left),
+        };
+        self.eat(self.current_token.clone())?;
+        let value = Box::new(self.parse_assignment()?);
+        let span = left.span.to(value.span);
+        let kind = match op {
+            None => NodeKind::Assign(Box::new(left), value),
+            Some(op) => NodeKind::CompoundAssign(Box::new(left), op, value),
+        };
+        Ok(ASTNode { kind, span, ty: None })
     }
 
-    fn parse_Conditions(&mut self) -> Option<ASTNode>{
-        let left = self.parse_logical_or_expression();
+    fn parse_Conditions(&mut self) -> Result<ASTNode, Diagnostic> {
+        let left = self.parse_logical_or_expression()?;
         if self.current_token.clone() == Token::Question{
-            self.eat(Token::Question);
-            let if_block = self.parse_expression().unwrap();
-            self.eat(Token::Colon);
-            let else_block = self.parse_Conditions().unwrap();
+            self.eat(Token::Question)?;
+            let if_block = self.parse_expression()?;
+            self.eat(Token::Colon)?;
+            let else_block = self.parse_Conditions()?;
 
-            Some(ASTNode::CondExp(Box::new(left), Box::new(if_block), Box::new(else_block)))
+            let span = left.span.to(else_block.span);
+            Ok(ASTNode { kind: NodeKind::CondExp(Box::new(left), Box::new(if_block), Box::new(else_block)), span, ty: None })
         }
         else{
-            Some(left)
+            Ok(left)
         }
     }
-    
-    
-    
-    fn parse_assignment_or_expression(&mut self) -> ASTNode {
-        let var_name = if let Token::Ident(name) = self.current_token.clone() {
-            self.eat(Token::Ident(name.clone()));
-            name
-        } else {
-            panic!("Expected an identifier");
-        };
-        
-        if self.current_token == Token::Assign {
-            self.eat(Token::Assign);
-            let expr = self.parse_expression().unwrap();
-            return ASTNode::Assign(var_name, Box::new(expr));
-        }
-        ASTNode::Var(var_name) // If no '=', it's just a variable reference
-    
-    }
 
     
 
 
-    fn parse_Assign_Or_declare(&mut self) -> ASTNode{
-        self.eat(Token::Keyword("int".to_string()));
+    fn parse_Assign_Or_declare(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        let specifiers = self.parse_specifiers()?;
+        if let Some(empty) = self.parse_tag_only(start, specifiers.tagged)? {
+            return Ok(empty);
+        }
+        if specifiers.typedef {
+            return self.parse_typedef(start, specifiers.read_only, specifiers.ty);
+        }
+        let (ty, read_only) = self.parse_pointers(specifiers.ty.clone(), specifiers.read_only);
         //we are expecting an identifier , a name , so we will handle it accordingly
         let var_name = if let Token::Ident(name) = self.current_token.clone() {
-            self.eat(Token::Ident(name.clone()));
+            self.eat(Token::Ident(name.clone()))?;
             name
         } else {
-            panic!("Expected an identifier after 'int'");
+            return Err(self.error_here("E0205", &format!("Expected an identifier after '{}'", ty)));
         };
+        self.parse_declarators(start, &specifiers, ty, read_only, var_name)
+    }
+
+    // the declarators of a variable declaration, the first one's name already eaten, up to the ';'.
+    // in `int a = 1, *p, b[2];` each one has its own pointers and array sizes on the same base type
+    fn parse_declarators(&mut self, start: Span, specifiers: &Specifiers, ty: CType, read_only: bool, name: String) -> Result<ASTNode, Diagnostic> {
+        let storage = specifiers.storage;
+        let mut declarations = vec![self.parse_declarator_rest(start, storage, read_only, ty, name)?];
+        while self.current_token == Token::Comma {
+            self.eat(Token::Comma)?;
+            let declarator_start = self.current_span;
+            let (ty, read_only) = self.parse_pointers(specifiers.ty.clone(), specifiers.read_only);
+            let Token::Ident(name) = self.current_token.clone() else {
+                return Err(self.error_here("E0205", &format!("Expected an identifier after '{}'", ty)));
+            };
+            self.eat(Token::Ident(name.clone()))?;
+            declarations.push(self.parse_declarator_rest(declarator_start, storage, read_only, ty, name)?);
+        }
+        //at the end of the declaration we are expecting a semi colomn ;
+        self.eat(Token::Semi)?;
+        if declarations.len() == 1 {
+            return Ok(declarations.remove(0));
+        }
+        Ok(self.node(NodeKind::DeclareList(declarations), start))
+    }
+
+    // typedef unsigned long size_t; typedef struct node Node, *NodeRef;
+    // the names go into the current scope as types, nothing is left in the tree
+    fn parse_typedef(&mut self, start: Span, read_only: bool, base: CType) -> Result<ASTNode, Diagnostic> {
+        loop {
+            let (ty, read_only) = self.parse_pointers(base.clone(), read_only);
+            let name_span = self.current_span;
+            let Token::Ident(name) = self.current_token.clone() else {
+                return Err(self.error_here("E0205", "Expected a name for the typedef"));
+            };
+            self.eat(Token::Ident(name.clone()))?;
+            let ty = self.parse_array_suffix(ty, &name)?;
+            self.declare_name(&name, Name::Typedef(ty, read_only), name_span)?;
+            if self.current_token != Token::Comma {
+                break;
+            }
+            self.eat(Token::Comma)?;
+        }
+        self.eat(Token::Semi)?;
+        Ok(self.node(NodeKind::Empty, start))
+    }
 
+    // the array sizes and the optional initializer of one declarator
+    fn parse_declarator_rest(&mut self, start: Span, storage: Storage, read_only: bool, ty: CType, name: String) -> Result<ASTNode, Diagnostic> {
+        // the name is in scope from here on, its own initializer already sees it
+        self.declare_name(&name, Name::Object, self.prev_span)?;
+        let ty = self.parse_array_suffix(ty, &name)?;
         let mut init_expr = None;
     
         // Check for optional assignment
         if self.current_token == Token::Assign {
-            self.eat(Token::Assign); // Consume '='
-            init_expr = Some(Box::new(self.parse_expression().unwrap())); // Parse the expression
+            self.eat(Token::Assign)?; // Consume '='
+            init_expr = Some(Box::new(self.parse_initializer()?));
         }
-        //at the end of the expressio we are expecting a semi colomn ;
-        self.eat(Token::Semi);
 
-        ASTNode::Declare(var_name, (init_expr))
+        Ok(self.node(NodeKind::Declare { name, ty, storage, read_only, init: init_expr }, start))
     }
 
     
@@ -270,54 +1124,90 @@ impl Parser
     
     
 
-    fn parse_logical_or_expression(&mut self) -> ASTNode{
-        let mut left = self.parse_logical_and_expression();
+    fn parse_logical_or_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_logical_and_expression()?;
         while let Token::LogOr = self.current_token{
             let op = match self.current_token{
                 Token::LogOr => BinaryOp::LogOr,
                 _ => unreachable!(),
             };
-            self.eat(self.current_token.clone());
-            let right = self.parse_logical_and_expression();
-            left = ASTNode::BinaryOp(Box::new(left) , op , Box::new(right));
+            self.eat(self.current_token.clone())?;
+            let right = self.parse_logical_and_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), op, Box::new(right)), span, ty: None };
         }
-        left
+        Ok(left)
     }
     
 
 
-    fn parse_logical_and_expression(&mut self) ->ASTNode{
-        let mut left = self.parse_equality_expression();
+    fn parse_logical_and_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_bitwise_or_expression()?;
         while let Token::LogAnd = self.current_token{
             let op = match self.current_token{
                 Token::LogAnd => BinaryOp::LogAnd,
                 _ => unreachable!(),
             };
-            self.eat(self.current_token.clone());
-            let right = self.parse_equality_expression();
-            left = ASTNode::BinaryOp(Box::new(left) , op , Box::new(right));
+            self.eat(self.current_token.clone())?;
+            let right = self.parse_bitwise_or_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), op, Box::new(right)), span, ty: None };
         }
-        left
+        Ok(left)
     }
 
-    fn parse_equality_expression(&mut self) ->ASTNode{
-        let mut left = self.parse_relational_expression();
+    fn parse_bitwise_or_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_bitwise_xor_expression()?;
+        while let Token::Pipe = self.current_token {
+            self.eat(Token::Pipe)?;
+            let right = self.parse_bitwise_xor_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), BinaryOp::BitOr, Box::new(right)), span, ty: None };
+        }
+        Ok(left)
+    }
+
+    fn parse_bitwise_xor_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_bitwise_and_expression()?;
+        while let Token::Caret = self.current_token {
+            self.eat(Token::Caret)?;
+            let right = self.parse_bitwise_and_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), BinaryOp::BitXor, Box::new(right)), span, ty: None };
+        }
+        Ok(left)
+    }
+
+    fn parse_bitwise_and_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_equality_expression()?;
+        while let Token::Ampersand = self.current_token {
+            self.eat(Token::Ampersand)?;
+            let right = self.parse_equality_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), BinaryOp::BitAnd, Box::new(right)), span, ty: None };
+        }
+        Ok(left)
+    }
+
+    fn parse_equality_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_relational_expression()?;
         while let Token::NEqualTo | Token::EqualTo = self.current_token{
             let op = match self.current_token{
                 Token::NEqualTo => BinaryOp::NotEq,
                 Token::EqualTo => BinaryOp::Equal,
                 _ => unreachable!(),
             };
-            self.eat(self.current_token.clone());
-            let right = self.parse_relational_expression();
-            left = ASTNode::BinaryOp(Box::new(left) , op , Box::new(right));
+            self.eat(self.current_token.clone())?;
+            let right = self.parse_relational_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), op, Box::new(right)), span, ty: None };
         }
-        left
+        Ok(left)
     }
 
-    fn parse_relational_expression(&mut self) -> ASTNode{
+    fn parse_relational_expression(&mut self) -> Result<ASTNode, Diagnostic> {
 
-        let mut left = self.parse_add_expression();
+        let mut left = self.parse_shift_expression()?;
         while let Token::Less | Token::GreatTh | Token::LessEq | Token::GreatThEq = self.current_token{
             let op = match self.current_token{
                 Token::Less => BinaryOp::Less,
@@ -326,105 +1216,382 @@ impl Parser
                 Token::GreatThEq => BinaryOp::GreaterEq,
                 _ => unreachable!(),
             };
-            self.eat(self.current_token.clone());
-            let right = self.parse_add_expression();
-            left = ASTNode::BinaryOp(Box::new(left) , op , Box::new(right));
+            self.eat(self.current_token.clone())?;
+            let right = self.parse_shift_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), op, Box::new(right)), span, ty: None };
+        }
+        Ok(left)
+    }
+
+    fn parse_shift_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_add_expression()?;
+        while let Token::ShiftLeft | Token::ShiftRight = self.current_token {
+            let op = match self.current_token {
+                Token::ShiftLeft => BinaryOp::ShiftLeft,
+                Token::ShiftRight => BinaryOp::ShiftRight,
+                _ => unreachable!(),
+            };
+            self.eat(self.current_token.clone())?;
+            let right = self.parse_add_expression()?;
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), op, Box::new(right)), span, ty: None };
         }
-        left
+        Ok(left)
     }
 
 
-    fn parse_add_expression(&mut self) ->ASTNode{
-        let mut node = self.parse_term(); // Start with the term (multiplication first)
+    fn parse_add_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut node = self.parse_term()?; // Start with the term (multiplication first)
         while let Token::Plus | Token::Minus = self.current_token {
             let op = match self.current_token {
                 Token::Plus => BinaryOp::Addition,
                 Token::Minus => BinaryOp::Subtraction, // Now we are sure this is subtraction, not negation
                 _ => unreachable!(),
             };
-            self.eat(self.current_token.clone());
-            let right = self.parse_term(); // Ensure proper precedence
-            node = ASTNode::BinaryOp(Box::new(node), op , Box::new(right));
+            self.eat(self.current_token.clone())?;
+            let right = self.parse_term()?; // Ensure proper precedence
+            let span = node.span.to(right.span);
+            node = ASTNode { kind: NodeKind::BinaryOp(Box::new(node), op, Box::new(right)), span, ty: None };
         }
-        node
+        Ok(node)
     }
 
-    fn parse_term(&mut self) -> ASTNode {
-        let mut left = self.parse_factor(); // Start with a factor
+    fn parse_term(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut left = self.parse_factor()?; // Start with a factor
     
-        while let Token::Star | Token::Slash = self.current_token {
+        while let Token::Star | Token::Slash | Token::Percent = self.current_token {
             let op = match self.current_token {
                 Token::Star => BinaryOp::Multiplication,
                 Token::Slash => BinaryOp::Division,
+                Token::Percent => BinaryOp::Modulo,
                 _ => unreachable!(),
             };
-            self.eat(self.current_token.clone()); // Consume the operator
-            let right = self.parse_factor(); // Parse the next factor
+            self.eat(self.current_token.clone())?; // Consume the operator
+            let right = self.parse_factor()?; // Parse the next factor
     
-            left = ASTNode::BinaryOp(Box::new(left), op, Box::new(right));
+            let span = left.span.to(right.span);
+            left = ASTNode { kind: NodeKind::BinaryOp(Box::new(left), op, Box::new(right)), span, ty: None };
         }
-        left
+        Ok(left)
     }
 
-    fn parse_factor(&mut self) -> ASTNode {
-        match self.current_token.clone() {
-            Token::Number(value) => {
-                self.eat(Token::Number(value));
-                ASTNode::Constant(value as i64)
-            }
-            Token::Minus | Token::bitwise | Token::logical => {
+    // unary operators, then a postfix expression
+    fn parse_factor(&mut self) -> Result<ASTNode, Diagnostic> {
+        match self.current_token {
+            Token::Minus | Token::Plus | Token::bitwise | Token::logical | Token::Star | Token::Ampersand => {
+                let start = self.current_span;
                 let op = match self.current_token {
                     Token::Minus => UnaryOp::Negate,
+                    Token::Plus => UnaryOp::Plus,
                     Token::bitwise => UnaryOp::BitNot,
                     Token::logical => UnaryOp::Not,
+                    Token::Star => UnaryOp::Deref,
+                    Token::Ampersand => UnaryOp::AddressOf,
                     _ => unreachable!(),
                 };
-                self.eat(self.current_token.clone());
-                let expr = self.parse_factor(); // Recursively parse next factor
-                ASTNode::UnaryOp(op, Box::new(expr))
+                self.eat(self.current_token.clone())?;
+                let expr = self.parse_factor()?; // Recursively parse next factor
+                Ok(self.node(NodeKind::UnaryOp(op, Box::new(expr)), start))
+            }
+            Token::Increment | Token::Decrement => {
+                let start = self.current_span;
+                let op = if self.current_token == Token::Increment { UnaryOp::PreIncrement } else { UnaryOp::PreDecrement };
+                self.eat(self.current_token.clone())?;
+                let expr = self.parse_factor()?;
+                Ok(self.node(NodeKind::UnaryOp(op, Box::new(expr)), start))
+            }
+            _ => self.parse_postfix_expression(),
+        }
+    }
+
+    fn parse_postfix_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let mut expr = self.parse_primary()?;
+        while let Token::Increment | Token::Decrement | Token::LParen | Token::LBracket | Token::Dot | Token::Arrow = self.current_token {
+            if self.current_token == Token::LParen {
+                expr = self.parse_call(expr)?;
+                continue;
+            }
+            // a[i] is *(a + i)
+            if self.current_token == Token::LBracket {
+                self.eat(Token::LBracket)?;
+                let index = self.parse_expression()?;
+                self.eat(Token::RBracket)?;
+                let span = expr.span.to(self.prev_span);
+                let address = ASTNode { kind: NodeKind::BinaryOp(Box::new(expr), BinaryOp::Addition, Box::new(index)), span, ty: None };
+                expr = ASTNode { kind: NodeKind::UnaryOp(UnaryOp::Deref, Box::new(address)), span, ty: None };
+                continue;
+            }
+            // s.x, and p->x which is (*p).x
+            if let Token::Dot | Token::Arrow = self.current_token {
+                let arrow = self.current_token == Token::Arrow;
+                self.eat(self.current_token.clone())?;
+                let Token::Ident(name) = self.current_token.clone() else {
+                    return Err(self.error_here("E0205", "Expected a member name"));
+                };
+                self.eat(Token::Ident(name.clone()))?;
+                let span = expr.span.to(self.prev_span);
+                if arrow {
+                    expr = ASTNode { kind: NodeKind::UnaryOp(UnaryOp::Deref, Box::new(expr)), span, ty: None };
+                }
+                expr = ASTNode { kind: NodeKind::Member(Box::new(expr), name), span, ty: None };
+                continue;
+            }
+            let op = if self.current_token == Token::Increment { UnaryOp::PostIncrement } else { UnaryOp::PostDecrement };
+            self.eat(self.current_token.clone())?;
+            let start = expr.span;
+            expr = self.node(NodeKind::UnaryOp(op, Box::new(expr)), start);
+        }
+        Ok(expr)
+    }
+
+    // callee(arg, arg). only functions called by name for now
+    fn parse_call(&mut self, callee: ASTNode) -> Result<ASTNode, Diagnostic> {
+        let NodeKind::Var(name) = callee.kind else {
+            return Err(Diagnostic::error("called object is not a function", callee.span).with_code("E0211"));
+        };
+        self.eat(Token::LParen)?;
+        let mut args = Vec::new();
+        while self.current_token != Token::RParen {
+            if !args.is_empty() {
+                self.eat(Token::Comma)?;
+            }
+            args.push(self.parse_assignment()?); // a ',' here separates arguments
+        }
+        self.eat(Token::RParen)?;
+        Ok(self.node(NodeKind::Call(name, args), callee.span))
+    }
+
+    fn parse_primary(&mut self) -> Result<ASTNode, Diagnostic> {
+        match self.current_token.clone() {
+            // 'a' is an int in C. char is signed here like in gcc on x86-64, so '\xff' is -1
+            Token::CharLiteral(byte) => {
+                let start = self.current_span;
+                self.eat(self.current_token.clone())?;
+                Ok(self.node(NodeKind::Constant(byte as i8 as i64, CType::Int), start))
+            }
+            // "a" "b" is the same string as "ab"
+            Token::StringLiteral(_) => {
+                let start = self.current_span;
+                let mut bytes = Vec::new();
+                while let Token::StringLiteral(part) = &self.current_token {
+                    bytes.extend_from_slice(part);
+                    self.eat(self.current_token.clone())?;
+                }
+                Ok(self.node(NodeKind::StringLiteral(bytes), start))
+            }
+            Token::FloatLiteral { value, suffix } => {
+                let start = self.current_span;
+                self.eat(self.current_token.clone())?;
+                let ty = types::float_literal_type(suffix);
+                // a float literal is rounded to float precision right away
+                let value = if ty == CType::Float { value as f32 as f64 } else { value };
+                Ok(self.node(NodeKind::FloatConstant(value, ty), start))
+            }
+            Token::IntLiteral { value, radix, suffix } => {
+                let start = self.current_span;
+                self.eat(self.current_token.clone())?;
+                match types::integer_literal_type(value, radix, suffix) {
+                    Some(ty) => Ok(self.node(NodeKind::Constant(value as i64, ty), start)),
+                    None => {
+                        let mut error = Diagnostic::error(format!("integer literal {} is too large for any signed type", value), start)
+                            .with_code("E0208");
+                        if radix == Radix::Decimal {
+                            error = error.with_fix(start.after(), "u", "add a 'u' suffix to make it unsigned");
+                        }
+                        Err(error)
+                    }
+                }
             }
             Token::LParen => {
-                self.eat(Token::LParen);
-                let expr = self.parse_expression().unwrap(); // Handle parentheses
-                self.eat(Token::RParen);
-                expr
+                let start = self.current_span;
+                self.eat(Token::LParen)?;
+                if self.at_type_name() {
+                    // a cast: (double) x, (int *) p
+                    let ty = self.parse_type()?;
+                    let (ty, _) = self.parse_pointers(ty, false);
+                    self.eat(Token::RParen)?;
+                    let expr = self.parse_factor()?;
+                    return Ok(self.node(NodeKind::Cast(ty, Box::new(expr)), start));
+                }
+                let expr = self.parse_expression()?; // Handle parentheses
+                self.eat(Token::RParen)?;
+                Ok(expr)
             }
             Token::Ident(var_name) => { // Handle return of variables.. like return a ...
+                let start = self.current_span;
                 let name = var_name.clone();
-                self.eat(Token::Ident(name.clone()));
-                ASTNode::Var(name)
+                self.eat(Token::Ident(name.clone()))?;
+                match self.enumerator(&name) {
+                    Some(value) => Ok(self.node(NodeKind::Constant(value, CType::Int), start)),
+                    None => Ok(self.node(NodeKind::Var(name), start)),
+                }
             }
             _ =>{
-                panic!("Unexpected token in factor")
+                Err(self.error_here("E0206", "Unexpected token in factor"))
             } 
         }
     }
     
-    fn parse_return(&mut self) -> ASTNode {
-        self.eat(Token::Keyword("return".to_string()));
-        let value = match self.parse_expression() {
-            Some(expr) => expr, // Valid expression
-            None => ASTNode::Constant(0), // Handle empty return
+    // if (cond) body [else body]. an else belongs to the nearest if without one,
+    // which is what parsing it right after the inner body gives us
+    fn parse_if(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("if".to_string()))?;
+        self.eat(Token::LParen)?;
+        let condition = self.parse_expression()?;
+        self.eat(Token::RParen)?;
+        let then_body = self.parse_body()?;
+        let else_body = if self.current_token == Token::Keyword("else".to_string()) {
+            self.eat(Token::Keyword("else".to_string()))?;
+            Some(self.parse_body()?)
+        } else {
+            None
+        };
+        Ok(self.node(NodeKind::Conditional(Box::new(condition), then_body, else_body), start))
+    }
+
+    // the body of a loop, where break and continue are allowed
+    fn parse_loop_body(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
+        self.loop_depth += 1;
+        let body = self.parse_body();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn parse_while(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("while".to_string()))?;
+        self.eat(Token::LParen)?;
+        let condition = self.parse_expression()?;
+        self.eat(Token::RParen)?;
+        let body = self.parse_loop_body()?;
+        Ok(self.node(NodeKind::While(Box::new(condition), body), start))
+    }
+
+    fn parse_do_while(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("do".to_string()))?;
+        let body = self.parse_loop_body()?;
+        self.eat(Token::Keyword("while".to_string()))?;
+        self.eat(Token::LParen)?;
+        let condition = self.parse_expression()?;
+        self.eat(Token::RParen)?;
+        self.eat(Token::Semi)?;
+        Ok(self.node(NodeKind::DoWhile(body, Box::new(condition)), start))
+    }
+
+    // for (init; condition; step) body, every clause can be left out
+    fn parse_for(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("for".to_string()))?;
+        self.enter_scope(); // a declaration in the init clause belongs to the loop
+        let result = self.parse_for_rest(start);
+        self.leave_scope();
+        result
+    }
+
+    fn parse_for_rest(&mut self, start: Span) -> Result<ASTNode, Diagnostic> {
+        self.eat(Token::LParen)?;
+        let init = if self.at_type_name() {
+            Some(Box::new(self.parse_Assign_Or_declare()?)) // eats the ';' too
+        } else if self.current_token == Token::Semi {
+            self.eat(Token::Semi)?;
+            None
+        } else {
+            Some(Box::new(self.parse_unused_expression()?))
+        };
+        let condition = if self.current_token == Token::Semi { None } else { Some(Box::new(self.parse_expression()?)) };
+        self.eat(Token::Semi)?;
+        let step = if self.current_token == Token::RParen { None } else { Some(Box::new(self.parse_expression()?)) };
+        self.eat(Token::RParen)?;
+        let body = self.parse_loop_body()?;
+        Ok(self.node(NodeKind::For { init, condition, step, body }, start))
+    }
+
+    // switch (value) body. the case labels can be anywhere in the body, even inside a loop in it
+    fn parse_switch(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("switch".to_string()))?;
+        self.eat(Token::LParen)?;
+        let value = self.parse_expression()?;
+        self.eat(Token::RParen)?;
+        self.switch_depth += 1;
+        let body = self.parse_body();
+        self.switch_depth -= 1;
+        Ok(self.node(NodeKind::Switch(Box::new(value), body?), start))
+    }
+
+    // case value: statement and default: statement. the value has to be an integer constant,
+    // checking it against the type of the switch and the other cases is for the semantic pass
+    fn parse_case(&mut self, keyword: &str) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        if self.switch_depth == 0 {
+            return Err(Diagnostic::error(format!("'{}' label not within a switch statement", keyword), start).with_code("E0225"));
+        }
+        self.eat(Token::Keyword(keyword.to_string()))?;
+        let value = if keyword == "case" {
+            let value = self.parse_Conditions()?;
+            if !matches!(constant::eval(&value), Some((constant::Value::Int(_), _))) {
+                return Err(Diagnostic::error("case label does not reduce to an integer constant", value.span).with_code("E0226"));
+            }
+            Some(Box::new(value))
+        } else {
+            None
+        };
+        self.eat(Token::Colon)?;
+        let span = start.to(self.prev_span);
+        let statement = Box::new(self.parse_statement()?);
+        let kind = match value {
+            Some(value) => NodeKind::Case(value, statement),
+            None => NodeKind::Default(statement),
+        };
+        Ok(ASTNode { kind, span, ty: None })
+    }
+
+    // break; and continue;
+    fn parse_jump(&mut self, keyword: &str) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        if keyword == "break" && self.loop_depth + self.switch_depth == 0 {
+            return Err(Diagnostic::error("'break' statement not in a loop or switch", start).with_code("E0210"));
+        }
+        if keyword == "continue" && self.loop_depth == 0 {
+            return Err(Diagnostic::error("'continue' statement not in a loop", start).with_code("E0210"));
+        }
+        self.eat(Token::Keyword(keyword.to_string()))?;
+        self.eat(Token::Semi)?;
+        let kind = if keyword == "break" { NodeKind::Break } else { NodeKind::Continue };
+        Ok(self.node(kind, start))
+    }
+
+    fn parse_return(&mut self) -> Result<ASTNode, Diagnostic> {
+        let start = self.current_span;
+        self.eat(Token::Keyword("return".to_string()))?;
+        let value = if self.current_token == Token::Semi {
+            None // Handle empty return
+        } else {
+            Some(Box::new(self.parse_expression()?)) // Valid expression
         };
     
-        self.eat(Token::Semi);
-        ASTNode::Return(Box::new(value))
+        self.eat(Token::Semi)?;
+        Ok(self.node(NodeKind::Return(value), start))
     }
 
 }
 
 
+#[allow(dead_code)]
 impl ASTNode {
     pub fn get_name(&self) -> Option<&String> {
-        if let ASTNode::Function { name, .. } = self {
+        if let NodeKind::Function { name, .. } = &self.kind {
             Some(name)
         } else {
             None
         }
     }
     pub fn get_body(&self) -> Option<&Vec<ASTNode>> {
-        if let ASTNode::Function { body, .. } = self {
-            Some(body)
+        if let NodeKind::Function { body, .. } = &self.kind {
+            body.as_ref()
         } else {
             None
         }
@@ -434,6 +1601,89 @@ impl ASTNode {
 
 
 
+fn is_type_keyword(keyword: &str) -> bool {
+    matches!(
+        keyword,
+        "void" | "char" | "short" | "int" | "long" | "signed" | "unsigned" | "float" | "double" | "struct" | "union" | "enum"
+    )
+}
+
+// the type named by a list of type keywords like `unsigned long int`, in whatever order they
+// were written. None if they don't make a type together (`short double`, `signed unsigned`)
+fn type_from_words(words: &[String]) -> Option<CType> {
+    let count = |word: &str| words.iter().filter(|w| *w == word).count();
+    let (signed, unsigned) = (count("signed"), count("unsigned"));
+    if signed + unsigned > 1 {
+        return None;
+    }
+    let mut rest: Vec<&str> = words.iter().map(String::as_str).filter(|w| *w != "signed" && *w != "unsigned").collect();
+    rest.sort_unstable();
+    let pick = |signed_ty: CType, unsigned_ty: CType| if unsigned == 1 { unsigned_ty } else { signed_ty };
+    Some(match (rest.join(" ").as_str(), signed + unsigned) {
+        ("void", 0) => CType::Void,
+        ("float", 0) => CType::Float,
+        // there is no long double, it is a double like the L suffix of a literal
+        ("double", 0) | ("double long", 0) => CType::Double,
+        ("char", _) => pick(CType::Char, CType::UChar),
+        ("short", _) | ("int short", _) => pick(CType::Short, CType::UShort),
+        ("int", _) | ("", 1) => pick(CType::Int, CType::UInt),
+        ("long", _) | ("int long", _) => pick(CType::Long, CType::ULong),
+        ("long long", _) | ("int long long", _) => pick(CType::LongLong, CType::ULongLong),
+        _ => return None,
+    })
+}
+
+// a keyword a declaration can start with
+fn is_declaration_keyword(keyword: &str) -> bool {
+    is_type_keyword(keyword) || matches!(keyword, "static" | "extern" | "const" | "typedef")
+}
+
+
+#[allow(dead_code)]
 pub fn prettyPrinting(ast : &ASTNode){
     println!("{:?}" , ast);
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the codes of the errors in source
+    fn errors(source: &str) -> Vec<&'static str> {
+        match Parser::new(Lexer::new(source)).parse() {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().filter_map(|error| error.code).collect(),
+        }
+    }
+
+    // whether each variable declared at file scope in source is const itself
+    fn read_only(source: &str) -> Vec<bool> {
+        let Ok(ASTNode { kind: NodeKind::Program(items), .. }) = Parser::new(Lexer::new(source)).parse() else {
+            panic!("{} doesn't parse", source);
+        };
+        items.iter()
+            .flat_map(|item| match &item.kind {
+                NodeKind::DeclareList(declarations) => declarations.iter().collect(),
+                _ => vec![item],
+            })
+            .filter_map(|item| match item.kind {
+                NodeKind::Declare { read_only, .. } => Some(read_only),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_const_before_the_star_is_about_what_is_pointed_to() {
+        assert_eq!(read_only("const char *s; char *const p; const char *const q;"), [false, true, true]);
+        assert_eq!(read_only("const int n, *p, *const q, **const *r;"), [true, false, true, false]);
+        assert_eq!(read_only("typedef const char *str; const str s; str t;"), [true, false]);
+    }
+
+    #[test]
+    fn an_unterminated_comment_ends_the_file_without_more_errors() {
+        assert_eq!(errors("int main(void) {\n  return 0; /* oops\n"), ["E0104"]);
+        assert_eq!(errors("int main(void) { return 0 }\n/* oops"), ["E0201", "E0104"]);
+    }
+}
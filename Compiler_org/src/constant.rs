@@ -0,0 +1,169 @@
+use crate::Parser::{ASTNode, BinaryOp, NodeKind, UnaryOp};
+use crate::types::{self, CType};
+
+// Constant expressions (C11 6.6): what an initializer of a global has to be, so the value can be
+// written into .data instead of computed at run time.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i64), // the bit pattern, already wrapped to its type
+    Float(f64),
+}
+
+impl Value {
+    // the value as it would be after converting it to `ty`
+    pub fn convert(self, ty: &CType) -> Value {
+        match (self, ty.is_floating()) {
+            (Value::Int(value), true) => Value::Float(value as f64),
+            (Value::Float(value), false) => Value::Int(wrap(value as i64, ty)),
+            (Value::Int(value), false) => Value::Int(wrap(value, ty)),
+            (Value::Float(value), true) if *ty == CType::Float => Value::Float(value as f32 as f64),
+            (value, true) => value,
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        match self {
+            Value::Int(value) => value == 0,
+            Value::Float(value) => value.to_bits() == 0, // -0.0 still needs its sign bit stored
+        }
+    }
+
+    fn is_true(self) -> bool {
+        match self {
+            Value::Int(value) => value != 0,
+            Value::Float(value) => value != 0.0,
+        }
+    }
+}
+
+// keeps the bits a value of type `ty` has, sign or zero extended to 64
+fn wrap(value: i64, ty: &CType) -> i64 {
+    match (ty.size(), ty.is_signed()) {
+        (1, true) => value as i8 as i64,
+        (1, false) => value as u8 as i64,
+        (2, true) => value as i16 as i64,
+        (2, false) => value as u16 as i64,
+        (4, true) => value as i32 as i64,
+        (4, false) => value as u32 as i64,
+        _ => value,
+    }
+}
+
+// the value and type of a constant expression, None if it isn't one
+// (it reads a variable, calls a function, divides by zero...)
+pub fn eval(node: &ASTNode) -> Option<(Value, CType)> {
+    match &node.kind {
+        NodeKind::Constant(value, ty) => Some((Value::Int(*value), ty.clone())),
+        NodeKind::FloatConstant(value, ty) => Some((Value::Float(*value), ty.clone())),
+        NodeKind::Cast(ty, expr) => {
+            let (value, _) = eval(expr)?;
+            Some((value.convert(ty), ty.clone()))
+        }
+        NodeKind::UnaryOp(op, expr) => {
+            let (value, ty) = eval(expr)?;
+            let ty = if ty.is_floating() { ty } else { types::common_type(&ty, &ty) };
+            let value = value.convert(&ty);
+            match (op, value) {
+                (UnaryOp::Plus, value) => Some((value, ty)),
+                (UnaryOp::Negate, Value::Int(value)) => Some((Value::Int(wrap(value.wrapping_neg(), &ty)), ty)),
+                (UnaryOp::Negate, Value::Float(value)) => Some((Value::Float(-value), ty)),
+                (UnaryOp::BitNot, Value::Int(value)) => Some((Value::Int(wrap(!value, &ty)), ty)),
+                (UnaryOp::Not, value) => Some((Value::Int(!value.is_true() as i64), CType::Int)),
+                _ => None,
+            }
+        }
+        NodeKind::BinaryOp(left, op, right) => eval_binary(left, op, right),
+        NodeKind::CondExp(condition, if_expr, else_expr) => {
+            let (condition, _) = eval(condition)?;
+            let (if_value, if_type) = eval(if_expr)?;
+            let (else_value, else_type) = eval(else_expr)?;
+            let ty = types::common_type(&if_type, &else_type);
+            let value = if condition.is_true() { if_value } else { else_value };
+            Some((value.convert(&ty), ty))
+        }
+        _ => None,
+    }
+}
+
+fn eval_binary(left: &ASTNode, op: &BinaryOp, right: &ASTNode) -> Option<(Value, CType)> {
+    let (left, left_type) = eval(left)?;
+    // && and || don't look at the right side once the left one decides
+    match (op, left.is_true()) {
+        (BinaryOp::LogAnd, false) => return Some((Value::Int(0), CType::Int)),
+        (BinaryOp::LogOr, true) => return Some((Value::Int(1), CType::Int)),
+        _ => {}
+    }
+    let (right, right_type) = eval(right)?;
+    if let BinaryOp::LogAnd | BinaryOp::LogOr = op {
+        return Some((Value::Int(right.is_true() as i64), CType::Int));
+    }
+
+    // shifts only promote the left side, everything else works in the common type
+    if let BinaryOp::ShiftLeft | BinaryOp::ShiftRight = op {
+        let ty = types::common_type(&left_type, &left_type);
+        let (Value::Int(value), Value::Int(amount)) = (left.convert(&ty), right) else {
+            return None;
+        };
+        if amount < 0 || amount >= ty.size() as i64 * 8 {
+            return None;
+        }
+        let value = match op {
+            BinaryOp::ShiftLeft => value.wrapping_shl(amount as u32),
+            _ if ty.is_signed() => value >> amount,
+            _ => ((value as u64) >> amount) as i64,
+        };
+        return Some((Value::Int(wrap(value, &ty)), ty));
+    }
+
+    let ty = types::common_type(&left_type, &right_type);
+    match (left.convert(&ty), right.convert(&ty)) {
+        (Value::Float(left), Value::Float(right)) => {
+            let value = match op {
+                BinaryOp::Addition => left + right,
+                BinaryOp::Subtraction => left - right,
+                BinaryOp::Multiplication => left * right,
+                BinaryOp::Division => left / right,
+                _ => return compare(op, left.partial_cmp(&right)),
+            };
+            Some((Value::Float(value).convert(&ty), ty))
+        }
+        (Value::Int(left), Value::Int(right)) => {
+            let unsigned = !ty.is_signed();
+            let value = match op {
+                BinaryOp::Addition => left.wrapping_add(right),
+                BinaryOp::Subtraction => left.wrapping_sub(right),
+                BinaryOp::Multiplication => left.wrapping_mul(right),
+                BinaryOp::Division | BinaryOp::Modulo if right == 0 => return None,
+                BinaryOp::Division if unsigned => ((left as u64) / (right as u64)) as i64,
+                BinaryOp::Modulo if unsigned => ((left as u64) % (right as u64)) as i64,
+                BinaryOp::Division => left.wrapping_div(right),
+                BinaryOp::Modulo => left.wrapping_rem(right),
+                BinaryOp::BitAnd => left & right,
+                BinaryOp::BitOr => left | right,
+                BinaryOp::BitXor => left ^ right,
+                _ if unsigned => return compare(op, Some((left as u64).cmp(&(right as u64)))),
+                _ => return compare(op, Some(left.cmp(&right))),
+            };
+            Some((Value::Int(wrap(value, &ty)), ty))
+        }
+        _ => None,
+    }
+}
+
+// comparisons give an int 0 or 1. None as the ordering means a NaN was involved
+fn compare(op: &BinaryOp, ordering: Option<std::cmp::Ordering>) -> Option<(Value, CType)> {
+    use std::cmp::Ordering::*;
+    let result = match (op, ordering) {
+        (BinaryOp::NotEq, None) => true,
+        (_, None) => false,
+        (BinaryOp::Equal, Some(ordering)) => ordering == Equal,
+        (BinaryOp::NotEq, Some(ordering)) => ordering != Equal,
+        (BinaryOp::Less, Some(ordering)) => ordering == Less,
+        (BinaryOp::LessEq, Some(ordering)) => ordering != Greater,
+        (BinaryOp::Greater, Some(ordering)) => ordering == Greater,
+        (BinaryOp::GreaterEq, Some(ordering)) => ordering != Less,
+        _ => return None, // the comma operator isn't allowed in a constant expression
+    };
+    Some((Value::Int(result as i64), CType::Int))
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use crate::Lexer::Span;
+
+// Everything the compiler wants to tell the user goes through a Diagnostic
+// instead of a panic, so main can print all of them and exit cleanly.
+//
+// Error codes are grouped by the stage that reports them:
+//   E01xx lexer, E02xx parser, E03xx semantic analysis and code generation
+// warnings use the same numbering with a W
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)] // nothing is reported as a note on its own yet
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+
+// a secondary place in the source worth pointing at, e.g. "previous declaration here"
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// a machine applicable edit: replace the text under span with replacement
+// (an empty span means insert at that position)
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub fixes: Vec<Fix>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            fixes: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self { severity: Severity::Warning, ..Self::error(message, span) }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_fix(mut self, span: Span, replacement: impl Into<String>, message: impl Into<String>) -> Self {
+        self.fixes.push(Fix { span, replacement: replacement.into(), message: message.into() });
+        self
+    }
+
+    // Renders the diagnostic the way rustc/clang do:
+    //
+    //   error: Redeclaration. invalid
+    //    --> file.c:3:3
+    //     |
+    //   3 |   int a = 3;
+    //     |   ^~~~~~~~~~
+    //     |
+    //   2 |   int a = 2;
+    //     |   ---------- previous declaration here
+    //     = note: ...
+    pub fn render(&self, file: &str, source: &str, color: bool) -> String {
+        let paint = Painter { color };
+        let severity_style = match self.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => CYAN,
+        };
+
+        let severity = match self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        };
+        let mut out = format!(
+            "{}{}\n",
+            paint.apply(severity_style, &severity),
+            paint.apply(BOLD, &format!(": {}", self.message))
+        );
+
+        // the gutter has to fit the biggest line number we are going to print
+        let max_line = self.labels.iter().map(|l| l.span.line).chain(std::iter::once(self.span.line)).max().unwrap_or(1);
+        let width = max_line.to_string().len();
+        let gutter = paint.apply(BLUE, &format!("{} |", " ".repeat(width)));
+
+        out.push_str(&format!("{}{} {}:{}:{}\n", " ".repeat(width), paint.apply(BLUE, "-->"), file, self.span.line, self.span.column));
+        out.push_str(&format!("{}\n", gutter));
+        out.push_str(&render_snippet(source, self.span, '^', '~', "", severity_style, width, &paint));
+
+        for label in &self.labels {
+            out.push_str(&format!("{}\n", gutter));
+            out.push_str(&render_snippet(source, label.span, '-', '-', &label.message, BLUE, width, &paint));
+        }
+
+        for note in &self.notes {
+            out.push_str(&format!("{} {} {}\n", " ".repeat(width), paint.apply(BLUE, "="), paint.apply(BOLD, &format!("note: {}", note))));
+        }
+        for fix in &self.fixes {
+            out.push_str(&format!("{} {} {}\n", " ".repeat(width), paint.apply(BLUE, "="), paint.apply(BOLD, &format!("help: {}", fix.message))));
+        }
+        out
+    }
+
+    // One line of JSON for editor plugins and CI annotations. Lines and columns are 1-based,
+    // end_line/end_column point one past the last character of the span.
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        let (end_line, end_column) = line_col(source, self.span.end);
+        let labels: Vec<String> = self.labels.iter().map(|label| {
+            format!("{{{},\"message\":{}}}", json_span(source, label.span), json_string(&label.message))
+        }).collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        let fixes: Vec<String> = self.fixes.iter().map(|fix| {
+            format!(
+                "{{{},\"replacement\":{},\"message\":{}}}",
+                json_span(source, fix.span), json_string(&fix.replacement), json_string(&fix.message)
+            )
+        }).collect();
+
+        format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{},\"labels\":[{}],\"notes\":[{}],\"fixes\":[{}]}}",
+            json_string(&self.severity.to_string()),
+            self.code.map(json_string).unwrap_or_else(|| "null".to_string()),
+            json_string(&self.message),
+            json_string(file),
+            self.span.line,
+            self.span.column,
+            end_line,
+            end_column,
+            labels.join(","),
+            notes.join(","),
+            fixes.join(",")
+        )
+    }
+}
+
+
+// 1-based line and column of a byte offset
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+fn json_span(source: &str, span: Span) -> String {
+    let (end_line, end_column) = line_col(source, span.end);
+    format!("\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}", span.line, span.column, end_line, end_column)
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+
+// prints the source line a span starts on with an underline under the span.
+// spans running over several lines are underlined to the end of their first line
+#[allow(clippy::too_many_arguments)]
+fn render_snippet(source: &str, span: Span, head: char, tail: char, label: &str, style: &str, width: usize, paint: &Painter) -> String {
+    let line_start = source[..span.start.min(source.len())].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = source[line_start..].find('\n').map(|i| line_start + i).unwrap_or(source.len());
+    let line_text = source[line_start..line_end].trim_end_matches('\r');
+
+    // keep tabs in the padding so the caret lines up with what the terminal shows
+    let start = span.start.clamp(line_start, line_start + line_text.len());
+    let end = span.end.clamp(start, line_start + line_text.len());
+    let padding: String = source[line_start..start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    let underline_len = source[start..end].chars().count().max(1);
+
+    let mut underline = head.to_string();
+    underline.push_str(&tail.to_string().repeat(underline_len - 1));
+    if !label.is_empty() {
+        underline.push(' ');
+        underline.push_str(label);
+    }
+
+    format!(
+        "{} {}\n{} {}{}\n",
+        paint.apply(BLUE, &format!("{:>width$} |", span.line, width = width)),
+        line_text,
+        paint.apply(BLUE, &format!("{} |", " ".repeat(width))),
+        padding,
+        paint.apply(style, &underline)
+    )
+}
+
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[1;36m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn apply(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
@@ -1,143 +1,364 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use crate::Parser::BinaryOp;
 use crate::Parser::UnaryOp;
 use crate::Parser::ASTNode;
+use crate::Parser::NodeKind;
+use crate::Parser::Param;
+use crate::Parser::Storage;
+use crate::constant;
+use crate::diagnostics::Diagnostic;
+use crate::initializer::{flatten_initializer, AddressOf, DataObject, InitValue, StaticInit};
+use crate::semantic::{binary_type, object_type, value_type as type_of};
+use crate::types::{self, ArgClass, CType};
+
+// Integer values are computed in %rax. float and double values live in %xmm0,
+// each in its own precision, and are converted when the types say so.
+// The program has been through the semantic pass: names are unique and every expression has its type.
+
+// the variables declared directly in one block
+struct Scope {
+    vars: HashMap<String, String>, // the memory operand of each variable, -8(%rbp)
+    stack_offset: i32, // where the stack was when the block started, its slots are free again after it
+}
 
 pub struct CodeGenContext {
-    var_offsets: HashMap<String, i32>,
+    scopes: Vec<Scope>, // innermost last, a name is looked up from the inside out
     stack_offset: i32,
+    frame_size: i32, // the deepest stack_offset of the function so far
+    return_type: CType, // of the function we are generating
+    return_slot: Option<String>, // where the caller wants a struct returned in memory, saved from %rdi
+    strings: Vec<Vec<u8>>, // string literals, emitted into .rodata as .LC<index>
+    loops: Vec<LoopLabels>, // the loops we are in, innermost last
+    case_labels: HashMap<usize, String>, // the label of each case and default, by where it starts in the source
+    jump_tables: Vec<String>, // the tables of the switches, emitted into .rodata
+    functions: HashMap<String, Vec<CType>>, // the parameter types of the functions declared so far
+    data: Vec<DataObject>, // the variables with static storage, as the semantic pass worked them out
+}
+
+// where break and continue jump to. a switch only has its own place to break to
+struct LoopLabels {
+    break_label: String,
+    continue_label: String,
 }
 
 impl CodeGenContext {
-    pub fn new() -> Self {
+    pub fn new(data: Vec<DataObject>) -> Self {
         Self {
-            var_offsets: HashMap::new(),
+            scopes: Vec::new(),
             stack_offset: 0, // Start at 0, grow downward (-8, -16, ...)
+            frame_size: 0,
+            return_type: CType::Int,
+            return_slot: None,
+            strings: Vec::new(),
+            loops: Vec::new(),
+            case_labels: HashMap::new(),
+            jump_tables: Vec::new(),
+            functions: HashMap::new(),
+            data,
         }
     }
 
-    pub fn allocate_var(&mut self, name: &str) -> i32 {
-        self.stack_offset -= 8; // Each variable gets 8 bytes
-        self.var_offsets.insert(name.to_string(), self.stack_offset);
-        self.stack_offset
+    // label of a string literal in .rodata, the same text shares one copy
+    pub fn intern_string(&mut self, bytes: &[u8]) -> String {
+        let index = match self.strings.iter().position(|s| s == bytes) {
+            Some(index) => index,
+            None => {
+                self.strings.push(bytes.to_vec());
+                self.strings.len() - 1
+            }
+        };
+        format!(".LC{}", index)
     }
 
-    pub fn enter_function(&mut self) {
-        self.stack_offset = 0; // Reset the stack offset for a new function
+    // a stack slot as big as the type, aligned the way the type wants
+    pub fn allocate_var(&mut self, name: &str, ty: CType) -> String {
+        let address = self.allocate_slot(ty.size(), ty.align());
+        self.add_local(name, address.clone());
+        address
     }
 
-    pub fn exit_function(&mut self) {
-        self.stack_offset = 0; // Reset when exiting
+    // an unnamed slot, for a struct a call returns. whole eightbytes, so registers can be stored as they are
+    fn allocate_temp(&mut self, ty: &CType) -> String {
+        self.allocate_slot(ty.size().div_ceil(8) * 8, ty.align().max(8))
+    }
+
+    fn allocate_slot(&mut self, size: u64, align: u64) -> String {
+        let (size, align) = (size.max(1) as i32, align.max(1) as i32);
+        self.stack_offset = (self.stack_offset - size).div_euclid(align) * align;
+        self.frame_size = self.frame_size.max(-self.stack_offset);
+        format!("{}(%rbp)", self.stack_offset)
+    }
+
+    // a name in the innermost block at an address that is already there: a struct parameter
+    // the caller left on the stack
+    fn add_local(&mut self, name: &str, address: String) {
+        let scope = self.scopes.last_mut().expect("variables are declared inside a function");
+        scope.vars.insert(name.to_string(), address);
     }
 
-    pub fn get_var_offset(&self, name: &str) -> i32 {
-        *self.var_offsets.get(name).unwrap_or_else(|| panic!("Variable {} not found", name))
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(Scope { vars: HashMap::new(), stack_offset: self.stack_offset });
     }
 
-    pub fn get_var_exist(&self , name : &str) -> i32{
-        if self.var_offsets.contains_key(name){
-            1
+    // the block's variables go out of scope and blocks after it reuse their slots
+    pub fn exit_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            self.stack_offset = scope.stack_offset;
         }
-        else{
-            0
+    }
+
+    pub fn enter_function(&mut self, return_type: CType) {
+        self.stack_offset = 0; // Reset the stack offset for a new function
+        self.frame_size = 0;
+        self.scopes.clear();
+        self.return_type = return_type;
+        self.return_slot = None;
+    }
+
+    // nothing of a function is visible in the next one
+    pub fn exit_function(&mut self) {
+        self.stack_offset = 0; // Reset when exiting
+        self.scopes.clear();
+    }
+
+    // a local or parameter is in a stack slot. anything else has static storage and its
+    // name is its label, a static in a block has a name of its own too
+    pub fn get_var_address(&self, name: &str) -> String {
+        match self.scopes.iter().rev().find_map(|scope| scope.vars.get(name)) {
+            Some(address) => address.clone(),
+            None => format!("{}(%rip)", name),
         }
     }
 }
 
 
-pub fn genASm(ast: &ASTNode, context: &mut CodeGenContext) -> String {
-    match ast {
+pub fn genASm(ast: &ASTNode, context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    match &ast.kind {
         // Program containing multiple functions
-        ASTNode::Program(functions) => {
-            let mut result = String::new();
+        NodeKind::Program(functions) => {
+            let mut result = String::from(".text\n");
             for func in functions {
-                result.push_str(&genASm(func, context));
+                result.push_str(&genASm(func, context)?);
+            }
+            for object in std::mem::take(&mut context.data) {
+                result.push_str(&emit_data(&object, context));
+            }
+            if !context.strings.is_empty() || !context.jump_tables.is_empty() {
+                result.push_str(".section .rodata\n");
+                for (index, bytes) in context.strings.iter().enumerate() {
+                    result.push_str(&format!(".LC{}:\n.string \"{}\"\n", index, escape_asm_string(bytes)));
+                }
+                for table in &context.jump_tables {
+                    result.push_str(table);
+                }
             }
-            result
+            // we never need an executable stack, without this note the linker assumes we do
+            result.push_str(".section .note.GNU-stack,\"\",@progbits\n");
+            Ok(result)
         }
 
         // Function Declaration
-        ASTNode::Function { name, body, return_type } => {
-            let mut result = format!(
-                ".global {}\n{}:\n\
-                pushq %rbp\n\
-                movq %rsp, %rbp\n",
-                name, name
-            );
+        NodeKind::Function { name, params, prototyped, body, return_type, storage, .. } => {
+            // int f(); doesn't forget the parameters a prototype before it gave
+            if *prototyped {
+                context.functions.insert(name.clone(), params.iter().map(|param| param.ty.clone()).collect());
+            }
+            let Some(body) = body else {
+                return Ok(String::new()); // a prototype, nothing to emit
+            };
 
-            context.enter_function(); // Reset stack offset tracking
+            context.enter_function(return_type.clone()); // Reset stack offset tracking
 
+            context.enter_scope();
+            let mut body_code = gen_params(params, context)?;
             for stmt in body {
-                result.push_str(&genASm(stmt, context));
+                body_code.push_str(&genASm(stmt, context)?);
+            }
+            context.exit_scope();
+
+            // every local has its slot from the start, so a declaration inside a branch that
+            // isn't taken can't leave the ones after it below %rsp. calls want %rsp 16 byte aligned
+            let frame_size = (context.frame_size + 15) / 16 * 16;
+            let mut result = String::new();
+            if *storage != Storage::Static {
+                result.push_str(&format!(".global {}\n", name));
+            }
+            result.push_str(&format!(
+                "{}:\n\
+                pushq %rbp\n\
+                movq %rsp, %rbp\n",
+                name
+            ));
+            if frame_size > 0 {
+                result.push_str(&format!("subq ${}, %rsp\n", frame_size));
             }
+            result.push_str(&body_code);
 
-            result.push_str("movq %rbp, %rsp\npopq %rbp\nret\n");
+            // falling off the end returns 0, which is what main needs
+            result.push_str("movq $0, %rax\nmovq %rbp, %rsp\npopq %rbp\nret\n");
             context.exit_function();
-            result
+            Ok(result)
         }
 
         // Variable Declaration
-        ASTNode::Declare(name, initializer) => {
-            if context.get_var_exist(name) == 1 {
-                panic!("Redeclaration. invalid")
+        NodeKind::Declare { name, ty, storage, init: initializer, .. } => {
+            // static storage is in the data the semantic pass gave us
+            if context.scopes.is_empty() || *storage != Storage::Default {
+                Ok(String::new())
+            }
+            else if ty.is_array() || initializer.as_ref().is_some_and(|init| matches!(init.kind, NodeKind::InitList(_))) {
+                // the whole variable is zeroed, then each value the initializer has is stored
+                let address = context.allocate_var(name, ty.clone());
+                let mut items = Vec::new();
+                if let Some(init) = initializer {
+                    flatten_initializer(ty, init, 0, &mut items)?;
+                }
+                let mut stores = Vec::new();
+                for item in &items {
+                    let value_code = match item.value {
+                        InitValue::Expr(expr) => format!("{}{}", genASm(expr, context)?, convert(&type_of(expr), &item.ty)),
+                        InitValue::Byte(byte) => format!("movq ${}, %rax\n", byte),
+                    };
+                    stores.push((value_code, item));
+                }
+                let mut result = String::new();
+                if initializer.is_some() {
+                    result.push_str(&format!("leaq {}, %rdi\nmovq ${}, %rcx\nxorl %eax, %eax\nrep stosb  # Zero {}\n", address, ty.size(), name));
+                }
+                for (value_code, item) in stores {
+                    result.push_str(&format!("{}leaq {}, %rcx\n{}  # Store into {}\n", value_code, address, store(&item.ty, &format!("{}(%rcx)", item.offset)), name));
+                }
+                Ok(result)
             }
             else{
-                let offset = context.allocate_var(name);
-                let mut result = format!("subq $8, %rsp  # Allocate space for {}\n", name);
+                let mut result = String::new();
 
-                if let Some(expr) = initializer {
-                    let expr_code = genASm(expr, context);
+                let address = context.allocate_var(name, ty.clone());
+                let init_code = match initializer {
+                    Some(expr) => Some(format!("{}{}", genASm(expr, context)?, convert(&type_of(expr), ty))),
+                    None => None,
+                };
+                if let Some(expr_code) = init_code {
                     result.push_str(&format!(
-                        "{}\nmovq %rax, {}(%rbp)  # Store value in {}\n",
-                        expr_code, offset, name
+                        "{}\n{}  # Store value in {}\n",
+                        expr_code, store(ty, &address), name
                     ));
                 }
 
-                result
+                Ok(result)
             }
         }
 
         // Variable Assignment
-        ASTNode::Assign(name, expr) => {
-            if context.get_var_exist(name) == 0 {
-                panic!("No such var")
-            } else {
-                // Normal assignment to an existing variable
-                let offset = context.get_var_offset(name);
-                let expr_code = genASm(expr, context);
-                return format!(
-                    "{}\nmovq %rax, {}(%rbp)  # Assign value to {}\n",
-                    expr_code, offset, name
-                );
-            }
+        NodeKind::Assign(target, expr) => {
+            let lvalue = gen_lvalue(target, context)?;
+            let expr_type = type_of(expr);
+            let expr_code = genASm(expr, context)?;
+            Ok(format!(
+                "{}{}{}{}{}  # Assign value\n",
+                lvalue.setup, expr_code, convert(&expr_type, &lvalue.ty), lvalue.reload, store(&lvalue.ty, &lvalue.address)
+            ))
+        }
+
+        // a op= e is a = a op e, with the lvalue computed once
+        NodeKind::CompoundAssign(target, op, expr) => {
+            let lvalue = gen_lvalue(target, context)?;
+            let ty = &lvalue.ty;
+            let value_type = type_of(expr);
+            let value_code = genASm(expr, context)?;
+            let load_code = format!("{}{}\n", lvalue.peek, load(ty, &lvalue.address));
+            let code = gen_arith(op, ty, &load_code, &value_type, &value_code);
+            Ok(format!(
+                "{}{}{}{}{}  # Update\n",
+                lvalue.setup, code, convert(&binary_type(op, ty, &value_type), ty), lvalue.reload, store(ty, &lvalue.address)
+            ))
         }
 
         // Return Statement
-        ASTNode::Return(value) => format!("{}\n", genASm(value, context)),
+        // float and double results go back in %xmm0, everything else in %rax
+        // only a void function returns without a value
+        NodeKind::Return(None) => Ok("movq %rbp, %rsp\npopq %rbp\nret\n".to_string()),
+
+        NodeKind::Return(Some(value)) => {
+            let value_code = genASm(value, context)?;
+            if context.return_type.is_struct() {
+                let return_type = context.return_type.clone();
+                return Ok(format!("{}{}movq %rbp, %rsp\npopq %rbp\nret\n", value_code, gen_struct_return(&return_type, context)));
+            }
+            Ok(format!(
+                "{}{}\nmovq %rbp, %rsp\npopq %rbp\nret\n",
+                value_code, convert(&type_of(value), &context.return_type)
+            ))
+        }
 
         // Wrapping expressions
-        ASTNode::Exp(expr) => genASm(expr, context),
+        NodeKind::Exp(expr) => genASm(expr, context),
 
         // Constants
-        ASTNode::Constant(val) => format!("movq ${}, %rax\n", val),
+        NodeKind::Constant(val, _) => {
+            // movq only takes a sign extended 32 bit immediate
+            if i32::try_from(*val).is_ok() {
+                Ok(format!("movq ${}, %rax\n", val))
+            } else {
+                Ok(format!("movabsq ${}, %rax\n", val))
+            }
+        }
+
+        // no literal pool: the bits go through an integer register
+        NodeKind::FloatConstant(val, ty) => {
+            if *ty == CType::Float {
+                Ok(format!("movl ${}, %eax  # {}\nmovd %eax, %xmm0\n", (*val as f32).to_bits(), val))
+            } else {
+                Ok(format!("movabsq ${}, %rax  # {}\nmovq %rax, %xmm0\n", val.to_bits(), val))
+            }
+        }
+
+        // the value of a string literal is the address of its first char
+        NodeKind::StringLiteral(bytes) => {
+            let label = context.intern_string(bytes);
+            Ok(format!("leaq {}(%rip), %rax\n", label))
+        }
+
+        NodeKind::InitList(_) => unreachable!("the parser only puts initializer lists in declarations"),
+
+        NodeKind::Empty => Ok(String::new()),
+
+        // the struct is somewhere in memory and %rax has its address, the member is at an offset from it
+        NodeKind::Member(base, name) => {
+            let member = member_of(base, name);
+            Ok(format!("{}{}  # Member {}\n", genASm(base, context)?, load(&member.ty, &format!("{}(%rax)", member.offset)), name))
+        }
+
+        NodeKind::Cast(ty, expr) => {
+            let expr_code = genASm(expr, context)?;
+            Ok(format!("{}{}", expr_code, convert(&type_of(expr), ty)))
+        }
 
         // Variable Usage
-        ASTNode::Var(name) => {
-            let offset = context.get_var_offset(name);
-            format!("movq {}(%rbp), %rax  # Load variable {}\n", offset, name)
+        NodeKind::Var(name) => {
+            let address = context.get_var_address(name);
+            Ok(format!("{}  # Load variable {}\n", load(&object_type(ast), &address), name))
         }
 
+        NodeKind::Call(name, args) => gen_call(name, args, &type_of(ast), context),
+
         // Binary Operations
-        ASTNode::BinaryOp(left, op, right) => {
-            let res1 = genASm(left, context);
-            let res2 = genASm(right, context);
+        NodeKind::BinaryOp(left, op, right) => {
+            let left_type = type_of(left);
+            let right_type = type_of(right);
+            let res1 = genASm(left, context)?;
+            let res2 = genASm(right, context)?;
 
             // Handle logical operators first (short-circuiting)
             if matches!(op, BinaryOp::LogAnd | BinaryOp::LogOr) {
                 let label_short_circuit = new_label("short_circuit");
                 let label_end = new_label("end");
+                // && and || look at each operand on its own, a double operand is tested against 0.0
+                let res1 = format!("{}{}", res1, truth_value(&left_type));
+                let res2 = format!("{}{}", res2, truth_value(&right_type));
 
-                return match op {
+                return Ok(match op {
                     BinaryOp::LogAnd => format!(
                         "{}\n\
                         testq %rax, %rax\n\
@@ -169,49 +390,1126 @@ pub fn genASm(ast: &ASTNode, context: &mut CodeGenContext) -> String {
                         label_short_circuit, label_end
                     ),
                     _ => unreachable!(), // Already checked matches!
-                };
+                });
             }
 
-            // Standard binary operations
-            let op_asm = match op {
-                BinaryOp::Addition => "addq %rcx, %rax\n",
-                BinaryOp::Subtraction => "subq %rcx, %rax\n",
-                BinaryOp::Multiplication => "imulq %rcx, %rax\n",
-                BinaryOp::Division => "movq $0, %rdx\nidiv %rcx\n",
-                BinaryOp::Equal => "cmpq %rcx, %rax\nsete %al\nmovzbq %al, %rax\n",
-                BinaryOp::NotEq => "cmpq %rcx, %rax\nsetne %al\nmovzbq %al, %rax\n",
-                BinaryOp::Less => "cmpq %rcx, %rax\nsetl %al\nmovzbq %al, %rax\n",
-                BinaryOp::LessEq => "cmpq %rcx, %rax\nsetle %al\nmovzbq %al, %rax\n",
-                BinaryOp::Greater => "cmpq %rcx, %rax\nsetg %al\nmovzbq %al, %rax\n",
-                BinaryOp::GreaterEq => "cmpq %rcx, %rax\nsetge %al\nmovzbq %al, %rax\n",
-                BinaryOp::LogAnd | BinaryOp::LogOr => unreachable!(), // Already handled above
-            };
+            // the left value is thrown away, the right one is the result
+            if let BinaryOp::Comma = op {
+                return Ok(format!("{}{}", res1, res2));
+            }
 
-            format!(
-                "{}\npushq %rax\n{}\npopq %rcx\n{}",
-                res1, res2, op_asm
-            )
+            Ok(gen_arith(op, &left_type, &res1, &right_type, &res2))
         }
 
         // Unary Operations
-        ASTNode::UnaryOp(op, expr) => {
-            let res = genASm(expr, context);
+        NodeKind::UnaryOp(op @ (UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement), expr) => {
+            let lvalue = gen_lvalue(expr, context)?;
+            let ty = &lvalue.ty;
+            let (step, save, restore) = if ty.is_floating() {
+                let s = float_suffix(ty);
+                let step = match op {
+                    UnaryOp::PreIncrement | UnaryOp::PostIncrement => "add",
+                    _ => "sub",
+                };
+                (
+                    format!("movl $1, %eax\ncvtsi2s{s}l %eax, %xmm1\n{step}s{s} %xmm1, %xmm0\n", s = s, step = step),
+                    "movaps %xmm0, %xmm2\n",
+                    "movaps %xmm2, %xmm0\n",
+                )
+            } else {
+                // a pointer moves by a whole element
+                let amount = ty.pointee().map_or(1, |pointee| pointee.size());
+                let step = match op {
+                    UnaryOp::PreIncrement | UnaryOp::PostIncrement => format!("addq ${}, %rax\n{}", amount, extend(ty)),
+                    _ => format!("subq ${}, %rax\n{}", amount, extend(ty)),
+                };
+                // not %rcx, that may hold the address
+                (step, "movq %rax, %rdx\n", "movq %rdx, %rax\n")
+            };
+            // the postfix forms keep the old value around to give it back
+            let (save, restore) = match op {
+                UnaryOp::PostIncrement | UnaryOp::PostDecrement => (save, restore),
+                _ => ("", ""),
+            };
+            Ok(format!(
+                "{}{}{}\n{}{}{}  # Update\n{}",
+                lvalue.setup, lvalue.reload, load(ty, &lvalue.address), save, step, store(ty, &lvalue.address), restore
+            ))
+        }
+
+        NodeKind::UnaryOp(UnaryOp::AddressOf, expr) => gen_address(expr, context),
+
+        NodeKind::UnaryOp(UnaryOp::Deref, pointer) => {
+            Ok(format!("{}{}\n", genASm(pointer, context)?, load(&object_type(ast), "(%rax)")))
+        }
+
+        NodeKind::UnaryOp(op, expr) => {
+            let res = genASm(expr, context)?;
+            let ty = type_of(expr);
+            if ty.is_floating() {
+                let suffix = float_suffix(&ty);
+                let op_asm = match op {
+                    // flip the sign bit
+                    UnaryOp::Negate if ty == CType::Float => "movd %xmm0, %eax\nxorl $0x80000000, %eax\nmovd %eax, %xmm0\n".to_string(),
+                    UnaryOp::Negate => "movq %xmm0, %rax\nbtcq $63, %rax\nmovq %rax, %xmm0\n".to_string(),
+                    // 0.0 and -0.0 are false, NaN is true
+                    UnaryOp::Not => format!("xorps %xmm1, %xmm1\nucomis{} %xmm1, %xmm0\nsete %al\nsetnp %cl\nandb %cl, %al\nmovzbq %al, %rax\n", suffix),
+                    UnaryOp::Plus => String::new(),
+                    _ => unreachable!(), // ~ takes integers only, ++, --, & and * are handled above
+                };
+                return Ok(format!("{}{}", res, op_asm));
+            }
+            // - and ~ work on the promoted operand, at its width
+            let promoted = types::common_type(&ty, &ty);
+            let (q, ax) = if promoted.size() == 8 { ("q", "%rax") } else { ("l", "%eax") };
             let op_asm = match op {
-                UnaryOp::Negate => "neg %rax\n",
-                UnaryOp::BitNot => "not %rax\n",
-                UnaryOp::Not => "cmp $0, %rax\nsete %al\nmovzbq %al, %rax\n",
+                UnaryOp::Negate => format!("{}neg{} {}\n{}", convert(&ty, &promoted), q, ax, extend(&promoted)),
+                UnaryOp::BitNot => format!("{}not{} {}\n{}", convert(&ty, &promoted), q, ax, extend(&promoted)),
+                UnaryOp::Not => "cmp $0, %rax\nsete %al\nmovzbq %al, %rax\n".to_string(),
+                UnaryOp::Plus => convert(&ty, &promoted),
+                _ => unreachable!(), // ++, --, & and * are handled above
+            };
+            Ok(format!("{}{}", res, op_asm))
+        }
+
+        NodeKind::Conditional(condition, then_body, else_body) => {
+            let label_else = new_label("else");
+            let label_end = new_label("end_if");
+            let mut result = format!(
+                "{}{}testq %rax, %rax\nje {}\n",
+                genASm(condition, context)?, truth_value(&type_of(condition)),
+                if else_body.is_some() { &label_else } else { &label_end }
+            );
+            result.push_str(&gen_block(then_body, context)?);
+            if let Some(else_body) = else_body {
+                result.push_str(&format!("jmp {}\n{}:\n", label_end, label_else));
+                result.push_str(&gen_block(else_body, context)?);
+            }
+            result.push_str(&format!("{}:\n", label_end));
+            Ok(result)
+        }
+
+        NodeKind::While(condition, body) => {
+            let label_start = new_label("while_start");
+            let label_end = new_label("while_end");
+            let mut result = format!(
+                "{}:\n{}{}testq %rax, %rax\nje {}\n",
+                label_start, genASm(condition, context)?, truth_value(&type_of(condition)), label_end
+            );
+            result.push_str(&gen_loop_body(body, &label_end, &label_start, context)?);
+            result.push_str(&format!("jmp {}\n{}:\n", label_start, label_end));
+            Ok(result)
+        }
+
+        NodeKind::DoWhile(body, condition) => {
+            let label_start = new_label("do_start");
+            let label_continue = new_label("do_continue");
+            let label_end = new_label("do_end");
+            let mut result = format!("{}:\n", label_start);
+            result.push_str(&gen_loop_body(body, &label_end, &label_continue, context)?);
+            result.push_str(&format!(
+                "{}:\n{}{}testq %rax, %rax\njne {}\n{}:\n",
+                label_continue, genASm(condition, context)?, truth_value(&type_of(condition)), label_start, label_end
+            ));
+            Ok(result)
+        }
+
+        // continue still runs the step. a variable declared in init lives in a scope around the loop
+        NodeKind::For { init, condition, step, body } => {
+            let label_start = new_label("for_start");
+            let label_continue = new_label("for_continue");
+            let label_end = new_label("for_end");
+            context.enter_scope();
+            let mut result = String::new();
+            if let Some(init) = init {
+                result.push_str(&genASm(init, context)?);
+            }
+            result.push_str(&format!("{}:\n", label_start));
+            if let Some(condition) = condition {
+                result.push_str(&format!(
+                    "{}{}testq %rax, %rax\nje {}\n",
+                    genASm(condition, context)?, truth_value(&type_of(condition)), label_end
+                ));
+            }
+            result.push_str(&gen_loop_body(body, &label_end, &label_continue, context)?);
+            result.push_str(&format!("{}:\n", label_continue));
+            if let Some(step) = step {
+                result.push_str(&genASm(step, context)?);
+            }
+            result.push_str(&format!("jmp {}\n{}:\n", label_start, label_end));
+            context.exit_scope();
+            Ok(result)
+        }
+
+        NodeKind::Block(body) => gen_block(body, context),
+
+        NodeKind::DeclareList(declarations) => declarations.iter().map(|declaration| genASm(declaration, context)).collect(),
+
+        NodeKind::Switch(value, body) => gen_switch(value, body, context),
+
+        NodeKind::Case(_, statement) | NodeKind::Default(statement) => {
+            let label = context.case_labels.remove(&ast.span.start).expect("the switch gave each of its labels a name");
+            Ok(format!("{}:\n{}", label, genASm(statement, context)?))
+        }
+
+        NodeKind::Break => {
+            let Some(labels) = context.loops.last() else {
+                unreachable!("the parser rejects break outside a loop");
             };
-            format!("{}{}", res, op_asm)
+            Ok(format!("jmp {}\n", labels.break_label))
+        }
+
+        NodeKind::Continue => {
+            let Some(labels) = context.loops.last() else {
+                unreachable!("the parser rejects continue outside a loop");
+            };
+            Ok(format!("jmp {}\n", labels.continue_label))
+        }
+
+        // only the chosen arm runs. both leave their value where a value of the result type goes,
+        // so the expression fits anywhere an operand does
+        NodeKind::CondExp(condition, if_expr, else_expr) => {
+            let ty = type_of(ast);
+            let label_else = new_label("cond_else");
+            let label_end = new_label("cond_end");
+            Ok(format!(
+                "{}{}testq %rax, %rax\nje {}\n{}{}jmp {}\n{}:\n{}{}{}:\n",
+                genASm(condition, context)?, truth_value(&type_of(condition)), label_else,
+                genASm(if_expr, context)?, convert(&type_of(if_expr), &ty), label_end,
+                label_else,
+                genASm(else_expr, context)?, convert(&type_of(else_expr), &ty),
+                label_end
+            ))
         }
     }
 }
 
 
-static mut LABEL_COUNT: usize = 0;
-fn new_label(base: &str) -> String {
-    unsafe {
-        LABEL_COUNT += 1;
-        format!(".{}_{}", base, LABEL_COUNT)
+// where an assignment or ++/-- writes. `setup` runs before the new value is computed and
+// `reload` after it, then `address` is valid. for a variable the address is fixed and both are
+// empty, for *p the pointer waits on the stack in between and comes back in %rcx
+struct Lvalue {
+    setup: String,
+    peek: String, // makes `address` valid without popping, to read the old value
+    reload: String,
+    address: String,
+    ty: CType,
+}
+
+fn gen_lvalue(node: &ASTNode, context: &mut CodeGenContext) -> Result<Lvalue, Diagnostic> {
+    match &node.kind {
+        NodeKind::Var(name) => Ok(Lvalue {
+            setup: String::new(),
+            peek: String::new(),
+            reload: String::new(),
+            address: context.get_var_address(name),
+            ty: object_type(node),
+        }),
+        NodeKind::UnaryOp(UnaryOp::Deref, pointer) => Ok(Lvalue {
+            setup: format!("{}pushq %rax\n", genASm(pointer, context)?),
+            peek: "movq (%rsp), %rcx\n".to_string(),
+            reload: "popq %rcx\n".to_string(),
+            address: "(%rcx)".to_string(),
+            ty: object_type(node),
+        }),
+        NodeKind::Member(base, name) => {
+            let member = member_of(base, name);
+            Ok(Lvalue {
+                setup: format!("{}pushq %rax\n", genASm(base, context)?),
+                peek: "movq (%rsp), %rcx\n".to_string(),
+                reload: "popq %rcx\n".to_string(),
+                address: format!("{}(%rcx)", member.offset),
+                ty: member.ty,
+            })
+        }
+        _ => unreachable!("the semantic pass only lets lvalues through"),
+    }
+}
+
+// &expr: the address of a variable, or &*p which is just p
+fn gen_address(node: &ASTNode, context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    match &node.kind {
+        NodeKind::Var(name) => {
+            let address = context.get_var_address(name);
+            Ok(format!("leaq {}, %rax  # Address of {}\n", address, name))
+        }
+        NodeKind::UnaryOp(UnaryOp::Deref, pointer) => genASm(pointer, context),
+        NodeKind::Member(base, name) => {
+            let member = member_of(base, name);
+            Ok(format!("{}leaq {}(%rax), %rax  # Address of {}\n", genASm(base, context)?, member.offset, name))
+        }
+        _ => unreachable!("the semantic pass only lets lvalues through"),
+    }
+}
+
+// the member `name` of the struct or union `base` is
+fn member_of(base: &ASTNode, name: &str) -> types::Member {
+    let CType::Struct(def) = type_of(base) else {
+        unreachable!("the semantic pass checked the member access");
+    };
+    def.member(name).expect("the semantic pass checked the member access")
+}
+
+// the statements of a loop body, with break and continue going to the given labels
+fn gen_loop_body(body: &[ASTNode], break_label: &str, continue_label: &str, context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    context.loops.push(LoopLabels { break_label: break_label.to_string(), continue_label: continue_label.to_string() });
+    let result = gen_block(body, context);
+    context.loops.pop();
+    result
+}
+
+// statements in a scope of their own
+fn gen_block(body: &[ASTNode], context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    context.enter_scope();
+    let result = body.iter().map(|stmt| genASm(stmt, context)).collect();
+    context.exit_scope();
+    result
+}
+
+
+// a switch with fewer cases than this compares them one by one
+const SWITCH_CHAIN_MAX: usize = 4;
+// a jump table has an entry for every value from the lowest case to the highest. it is used
+// when at least one entry in this many is a case and the table isn't too big
+const SWITCH_TABLE_SPARSENESS: i128 = 3;
+const SWITCH_TABLE_MAX: i128 = 4096;
+
+// the value is compared and we jump into the body, which is generated once as it is written so
+// every case falls through into the next. break jumps past it, continue still means the loop around
+fn gen_switch(value: &ASTNode, body: &[ASTNode], context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    let ty = type_of(value);
+    let promoted = types::common_type(&ty, &ty);
+    let label_end = new_label("switch_end");
+    let mut cases = Vec::new();
+    let mut default = None;
+    for stmt in body {
+        collect_cases(stmt, promoted.is_signed(), &mut cases, &mut default, context);
+    }
+    cases.sort_unstable_by_key(|(value, _)| *value);
+
+    let mut result = format!("{}{}", genASm(value, context)?, convert(&ty, &promoted));
+    let default = default.unwrap_or_else(|| label_end.clone());
+    let range = match (cases.first(), cases.last()) {
+        (Some((low, _)), Some((high, _))) => high - low + 1,
+        _ => 0,
+    };
+    if cases.len() < SWITCH_CHAIN_MAX {
+        result.push_str(&gen_case_chain(&cases, &default));
+    } else if range <= cases.len() as i128 * SWITCH_TABLE_SPARSENESS && range <= SWITCH_TABLE_MAX {
+        result.push_str(&gen_jump_table(&cases, &default, context));
+    } else {
+        result.push_str(&gen_case_search(&cases, promoted.is_signed(), &default));
+    }
+
+    let continue_label = context.loops.last().map(|labels| labels.continue_label.clone()).unwrap_or_default();
+    result.push_str(&gen_loop_body(body, &label_end, &continue_label, context)?);
+    result.push_str(&format!("{}:\n", label_end));
+    Ok(result)
+}
+
+// gives every case and default of a switch a label, not those of a switch nested in it. the
+// values come back in the order of the switch type, as an i128 so unsigned ones sort right
+fn collect_cases(node: &ASTNode, signed: bool, cases: &mut Vec<(i128, String)>, default: &mut Option<String>, context: &mut CodeGenContext) {
+    let nested: Vec<&ASTNode> = match &node.kind {
+        NodeKind::Case(value, statement) => {
+            let NodeKind::Constant(value, _) = value.kind else {
+                unreachable!("the semantic pass turns a case value into a constant");
+            };
+            let label = new_label("case");
+            context.case_labels.insert(node.span.start, label.clone());
+            cases.push((if signed { value as i128 } else { value as u64 as i128 }, label));
+            vec![statement]
+        }
+        NodeKind::Default(statement) => {
+            let label = new_label("default");
+            context.case_labels.insert(node.span.start, label.clone());
+            *default = Some(label);
+            vec![statement]
+        }
+        NodeKind::Conditional(_, then_body, else_body) => then_body.iter().chain(else_body.iter().flatten()).collect(),
+        NodeKind::While(_, body) | NodeKind::DoWhile(body, _) | NodeKind::For { body, .. } | NodeKind::Block(body) => body.iter().collect(),
+        _ => Vec::new(),
+    };
+    for stmt in nested {
+        collect_cases(stmt, signed, cases, default, context);
+    }
+}
+
+// cmpq against a case value. an immediate is only 32 bits, sign extended
+fn compare_case(value: i128) -> String {
+    let value = value as i64;
+    if i32::try_from(value).is_ok() {
+        format!("cmpq ${}, %rax\n", value)
+    } else {
+        format!("movabsq ${}, %rcx\ncmpq %rcx, %rax\n", value)
+    }
+}
+
+fn gen_case_chain(cases: &[(i128, String)], default: &str) -> String {
+    let mut result = String::new();
+    for (value, label) in cases {
+        result.push_str(&format!("{}je {}\n", compare_case(*value), label));
+    }
+    result.push_str(&format!("jmp {}\n", default));
+    result
+}
+
+// compares against the middle case, then looks in the half the value is in
+fn gen_case_search(cases: &[(i128, String)], signed: bool, default: &str) -> String {
+    if cases.len() < SWITCH_CHAIN_MAX {
+        return gen_case_chain(cases, default);
+    }
+    let middle = cases.len() / 2;
+    let (value, label) = &cases[middle];
+    let label_lower = new_label("switch_lower");
+    format!(
+        "{}je {}\n{} {}\n{}{}:\n{}",
+        compare_case(*value), label, if signed { "jl" } else { "jb" }, label_lower,
+        gen_case_search(&cases[middle + 1..], signed, default), label_lower, gen_case_search(&cases[..middle], signed, default)
+    )
+}
+
+// the value minus the lowest case indexes a table of offsets from the table to the code. a
+// value below the lowest case wraps around to a big unsigned index, so one compare catches both ends
+fn gen_jump_table(cases: &[(i128, String)], default: &str, context: &mut CodeGenContext) -> String {
+    let label_table = new_label("switch_table");
+    let (low, high) = (cases[0].0, cases[cases.len() - 1].0);
+    let mut table = format!(".align 4\n{}:\n", label_table);
+    let mut next = cases.iter().peekable();
+    for value in low..=high {
+        let target = match next.next_if(|(case, _)| *case == value) {
+            Some((_, label)) => label.as_str(),
+            None => default,
+        };
+        table.push_str(&format!(".long {}-{}\n", target, label_table));
+    }
+    context.jump_tables.push(table);
+
+    let low = low as i64;
+    let subtract = match low {
+        0 => String::new(),
+        _ if i32::try_from(low).is_ok() => format!("subq ${}, %rax\n", low),
+        _ => format!("movabsq ${}, %rcx\nsubq %rcx, %rax\n", low),
+    };
+    format!(
+        "{}cmpq ${}, %rax\nja {}\nleaq {}(%rip), %rcx\nmovslq (%rcx,%rax,4), %rax\naddq %rcx, %rax\njmp *%rax\n",
+        subtract, high as i64 - low, default, label_table
+    )
+}
+
+const ARG_REGISTERS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+const SSE_ARG_REGISTERS: usize = 8; // %xmm0 - %xmm7
+
+// where the System V ABI puts each argument: integers in the next free general purpose register,
+// float and double in the next free xmm register, whatever doesn't fit goes on the stack in order
+#[derive(Debug, PartialEq)]
+enum ArgLocation {
+    Register(&'static str),
+    Sse(usize),
+    Stack(usize), // index of the 8 byte slot above the return address
+}
+
+// the classes of the eightbytes of a value, None when it goes in memory
+fn eightbyte_classes(ty: &CType) -> Option<Vec<ArgClass>> {
+    match ty {
+        CType::Struct(_) => types::classify(ty),
+        _ if ty.is_floating() => Some(vec![ArgClass::Sse]),
+        _ => Some(vec![ArgClass::Integer]),
+    }
+}
+
+// a struct the caller gets back through a pointer it passes in %rdi
+fn returns_in_memory(ty: &CType) -> bool {
+    ty.is_struct() && types::classify(ty).is_none()
+}
+
+// the location of each eightbyte of each argument. a struct goes in registers only if all of
+// it fits, otherwise all of it goes on the stack. hidden_return takes %rdi for the return pointer
+fn classify_args(types: &[CType], hidden_return: bool) -> Vec<Vec<ArgLocation>> {
+    let (mut ints, mut sses, mut stack) = (hidden_return as usize, 0, 0);
+    types.iter().map(|ty| {
+        if let Some(classes) = eightbyte_classes(ty) {
+            let need_ints = classes.iter().filter(|class| **class == ArgClass::Integer).count();
+            let need_sses = classes.len() - need_ints;
+            if ints + need_ints <= ARG_REGISTERS.len() && sses + need_sses <= SSE_ARG_REGISTERS {
+                return classes.iter().map(|class| match class {
+                    ArgClass::Integer => {
+                        ints += 1;
+                        ArgLocation::Register(ARG_REGISTERS[ints - 1])
+                    }
+                    ArgClass::Sse => {
+                        sses += 1;
+                        ArgLocation::Sse(sses - 1)
+                    }
+                }).collect();
+            }
+        }
+        let slots = ty.size().div_ceil(8).max(1) as usize;
+        stack += slots;
+        (stack - slots..stack).map(ArgLocation::Stack).collect()
+    }).collect()
+}
+
+// writes the low `bytes` bytes of %rax to offset(%r11). the last eightbyte of a struct
+// may be short, and the bytes after it belong to something else
+fn store_partial(bytes: u64, offset: u64) -> String {
+    let mut code = String::new();
+    let mut done = 0;
+    while done < bytes {
+        let (width, mov, register) = match bytes - done {
+            8.. => (8, "movq", "%rax"),
+            4..=7 => (4, "movl", "%eax"),
+            2..=3 => (2, "movw", "%ax"),
+            _ => (1, "movb", "%al"),
+        };
+        code.push_str(&format!("{} {}, {}(%r11)\n", mov, register, offset + done));
+        done += width;
+        if done < bytes {
+            code.push_str(&format!("shrq ${}, %rax\n", width * 8));
+        }
     }
+    code
 }
 
+// reads `bytes` bytes at offset(%r11) into a 64 bit register, the counterpart of store_partial
+fn load_partial(bytes: u64, offset: u64, register: &str) -> String {
+    if bytes >= 8 {
+        return format!("movq {}(%r11), {}\n", offset, register);
+    }
+    // from the last byte down, shifting each one in from the bottom
+    let mut code = format!("xorl %r10d, %r10d\nmovq %r10, {}\n", register);
+    for index in (0..bytes).rev() {
+        code.push_str(&format!("shlq $8, {r}\nmovzbq {}(%r11), %r10\norq %r10, {r}\n", offset + index, r = register));
+    }
+    code
+}
+
+// %rax points at the struct to return. small ones go back in %rax/%rdx and %xmm0/%xmm1,
+// big ones are copied to where the caller said and their address returned
+fn gen_struct_return(ty: &CType, context: &CodeGenContext) -> String {
+    let Some(classes) = types::classify(ty) else {
+        let slot = context.return_slot.as_deref().expect("a function returning a struct in memory saves %rdi");
+        return format!("movq %rax, %rsi\nmovq {slot}, %rdi\nmovq ${}, %rcx\nrep movsb\nmovq {slot}, %rax\n", ty.size(), slot = slot);
+    };
+    let mut code = String::from("movq %rax, %r11\n");
+    let (mut ints, mut sses) = (["%rax", "%rdx"].iter(), 0);
+    for (index, class) in classes.iter().enumerate() {
+        let offset = 8 * index as u64;
+        let bytes = (ty.size() - offset).min(8);
+        match class {
+            ArgClass::Integer => code.push_str(&load_partial(bytes, offset, ints.next().unwrap())),
+            ArgClass::Sse => {
+                let mov = if bytes == 4 { "movss" } else { "movq" };
+                code.push_str(&format!("{} {}(%r11), %xmm{}\n", mov, offset, sses));
+                sses += 1;
+            }
+        }
+    }
+    code
+}
+
+// moves the incoming arguments into local slots, so the body uses them like any other variable
+fn gen_params(params: &[Param], context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    let types: Vec<CType> = params.iter().map(|param| param.ty.clone()).collect();
+    let mut result = String::new();
+    let hidden_return = returns_in_memory(&context.return_type);
+    if hidden_return {
+        let slot = context.allocate_temp(&CType::Long);
+        result.push_str(&format!("movq %rdi, {}  # Where the struct is returned\n", slot));
+        context.return_slot = Some(slot);
+    }
+    for (param, locations) in params.iter().zip(classify_args(&types, hidden_return)) {
+        let name = param.name.as_deref().expect("the parser makes sure the parameters of a definition have names");
+        // above the saved %rbp and the return address
+        let stack_address = |slot: usize| format!("{}(%rbp)", 16 + 8 * slot);
+        if param.ty.is_struct() {
+            // a struct on the stack is the caller's copy already, it can stay there
+            if let ArgLocation::Stack(slot) = locations[0] {
+                context.add_local(name, stack_address(slot));
+                continue;
+            }
+            let address = context.allocate_var(name, param.ty.clone());
+            result.push_str(&format!("leaq {}, %r11\n", address));
+            for (index, location) in locations.iter().enumerate() {
+                let offset = 8 * index as u64;
+                match location {
+                    ArgLocation::Register(register) => result.push_str(&format!("movq {}, %rax\n", register)),
+                    ArgLocation::Sse(xmm) => result.push_str(&format!("movq %xmm{}, %rax\n", xmm)),
+                    ArgLocation::Stack(_) => unreachable!("a struct is either all in registers or all on the stack"),
+                }
+                result.push_str(&store_partial((param.ty.size() - offset).min(8), offset));
+            }
+            result.push_str(&format!("  # Parameter {}\n", name));
+            continue;
+        }
+        let address = context.allocate_var(name, param.ty.clone());
+        let fetch = match locations[0] {
+            ArgLocation::Register(register) => format!("movq {}, %rax\n", register),
+            ArgLocation::Sse(index) => format!("movaps %xmm{}, %xmm0\n", index),
+            ArgLocation::Stack(slot) => format!("{}\n", load(&param.ty, &stack_address(slot))),
+        };
+        result.push_str(&format!("{}{}{}  # Parameter {}\n", fetch, extend(&param.ty), store(&param.ty, &address), name));
+    }
+    Ok(result)
+}
+
+// A call. The arguments are evaluated left to right and pushed, then copied to where the ABI
+// wants them. The stack depth at this point depends on the expression we are in, so %rsp is
+// aligned to 16 at run time and the old value kept just above the stack arguments.
+// A struct argument is pushed whole, one eightbyte per slot.
+fn gen_call(name: &str, args: &[ASTNode], return_type: &CType, context: &mut CodeGenContext) -> Result<String, Diagnostic> {
+    let mut result = String::new();
+    let mut types = Vec::new();
+    for (index, arg) in args.iter().enumerate() {
+        let arg_type = type_of(arg);
+        // arguments without a known parameter type get the default promotions, float becomes double
+        let ty = match context.functions.get(name).and_then(|params| params.get(index)) {
+            Some(param_type) => param_type.clone(),
+            None if arg_type == CType::Float => CType::Double,
+            None => types::common_type(&arg_type, &arg_type),
+        };
+        result.push_str(&genASm(arg, context)?);
+        result.push_str(&convert(&arg_type, &ty));
+        if ty.is_struct() {
+            let size = ty.size().div_ceil(8) * 8;
+            result.push_str(&format!("subq ${}, %rsp\nmovq %rax, %rsi\nmovq %rsp, %rdi\nmovq ${}, %rcx\nrep movsb\n", size, ty.size()));
+        } else if ty.is_floating() {
+            result.push_str(&format!("subq $8, %rsp\nmovs{} %xmm0, (%rsp)\n", float_suffix(&ty)));
+        } else {
+            result.push_str("pushq %rax\n");
+        }
+        types.push(ty);
+    }
+
+    let hidden_return = returns_in_memory(return_type);
+    // a struct that comes back is kept in a slot of our frame
+    let return_temp = return_type.is_struct().then(|| context.allocate_temp(return_type));
+
+    let locations = classify_args(&types, hidden_return);
+    let slots: Vec<usize> = types.iter().map(|ty| ty.size().div_ceil(8).max(1) as usize).collect();
+    let total_slots: usize = slots.iter().sum();
+    let stack_slots = locations.iter().flatten().filter(|location| matches!(location, ArgLocation::Stack(_))).count();
+    result.push_str(&format!(
+        "movq %rsp, %rax\nsubq ${}, %rsp\nandq $-16, %rsp\nmovq %rax, {}(%rsp)\n",
+        8 * stack_slots + 8, 8 * stack_slots
+    ));
+    // argument i was pushed i-th, so the ones after it are below it. eightbyte j of it is 8j above its start
+    let pushed = |index: usize, eightbyte: usize| 8 * (slots[index + 1..].iter().sum::<usize>() + eightbyte);
+    let mut sse_count = 0;
+    for (index, arg_locations) in locations.iter().enumerate() {
+        for (eightbyte, location) in arg_locations.iter().enumerate() {
+            if let ArgLocation::Stack(slot) = location {
+                result.push_str(&format!("movq {}(%rax), %r11\nmovq %r11, {}(%rsp)\n", pushed(index, eightbyte), 8 * slot));
+            }
+        }
+    }
+    for (index, arg_locations) in locations.iter().enumerate() {
+        for (eightbyte, location) in arg_locations.iter().enumerate() {
+            match location {
+                ArgLocation::Register(register) => result.push_str(&format!("movq {}(%rax), {}\n", pushed(index, eightbyte), register)),
+                ArgLocation::Sse(xmm) => {
+                    let mov = if types[index].is_struct() { "movq".to_string() } else { format!("movs{}", float_suffix(&types[index])) };
+                    result.push_str(&format!("{} {}(%rax), %xmm{}\n", mov, pushed(index, eightbyte), xmm));
+                    sse_count += 1;
+                }
+                ArgLocation::Stack(_) => {}
+            }
+        }
+    }
+    if let (true, Some(temp)) = (hidden_return, &return_temp) {
+        result.push_str(&format!("leaq {}, %rdi\n", temp));
+    }
+
+    // %al tells a variadic function like printf how many xmm registers carry arguments
+    result.push_str(&format!("movl ${}, %eax\ncall {}@PLT\nmovq {}(%rsp), %rsp\n", sse_count, name, 8 * stack_slots));
+    if total_slots > 0 {
+        result.push_str(&format!("addq ${}, %rsp\n", 8 * total_slots));
+    }
+    if let Some(temp) = return_temp {
+        // the registers it came back in go to the slot, then we hold it by its address like any struct
+        if let Some(classes) = types::classify(return_type) {
+            result.push_str(&format!("leaq {}, %r11\n", temp));
+            let (mut ints, mut sses) = (["%rax", "%rdx"].iter(), 0);
+            for (index, class) in classes.iter().enumerate() {
+                match class {
+                    ArgClass::Integer => result.push_str(&format!("movq {}, {}(%r11)\n", ints.next().unwrap(), 8 * index)),
+                    ArgClass::Sse => {
+                        result.push_str(&format!("movq %xmm{}, {}(%r11)\n", sses, 8 * index));
+                        sses += 1;
+                    }
+                }
+            }
+        }
+        result.push_str(&format!("leaq {}, %rax\n", temp));
+    }
+    result.push_str(extend(return_type));
+    Ok(result)
+}
+
+// an integer narrower than 64 bits is kept in %rax sign or zero extended to the full register.
+// this cuts the value in %rax down to ty and widens it back: after 32 bit arithmetic, on a
+// conversion, and for values from code gcc compiled, which leaves the upper bits undefined
+fn extend(ty: &CType) -> &'static str {
+    match ty {
+        CType::Char => "movsbq %al, %rax\n",
+        CType::UChar => "movzbl %al, %eax\n",
+        CType::Short => "movswq %ax, %rax\n",
+        CType::UShort => "movzwl %ax, %eax\n",
+        CType::Int => "cltq\n",
+        CType::UInt => "movl %eax, %eax\n",
+        _ => "",
+    }
+}
+
+
+// arithmetic, bitwise and comparison operators once both operands are generated.
+// left and right are the code computing each operand, the result is left in %rax or %xmm0
+fn gen_arith(op: &BinaryOp, left_type: &CType, left: &str, right_type: &CType, right: &str) -> String {
+    if left_type.is_pointer() || right_type.is_pointer() {
+        return gen_pointer_arith(op, left_type, left, right_type, right);
+    }
+
+    let common = types::common_type(left_type, right_type);
+    if common.is_floating() {
+        return gen_float_binary(op, &common, left, &convert(left_type, &common), right, &convert(right_type, &common));
+    }
+
+    // the operation runs at the width of its type, so int wraps at 32 bits like in gcc.
+    // a shift count is only read from %cl and keeps its own type
+    let shift = matches!(op, BinaryOp::ShiftLeft | BinaryOp::ShiftRight);
+    let ty = if shift { types::common_type(left_type, left_type) } else { common };
+    let left_conv = convert(left_type, &ty);
+    let right_conv = if shift { String::new() } else { convert(right_type, &ty) };
+    let (q, ax, cx, dx) = if ty.size() == 8 { ("q", "%rax", "%rcx", "%rdx") } else { ("l", "%eax", "%ecx", "%edx") };
+    let signed = ty.is_signed();
+    let divide = match (signed, ty.size()) {
+        (true, 8) => "cqto\nidivq %rcx\n".to_string(),
+        (true, _) => "cltd\nidivl %ecx\n".to_string(),
+        (false, _) => format!("xorl %edx, %edx\ndiv{} {}\n", q, cx),
+    };
+    let op_asm = match op {
+        BinaryOp::Addition => format!("add{} {}, {}\n", q, cx, ax),
+        BinaryOp::Subtraction => format!("sub{} {}, {}\n", q, cx, ax),
+        BinaryOp::Multiplication => format!("imul{} {}, {}\n", q, cx, ax),
+        BinaryOp::Division => divide,
+        BinaryOp::Modulo => format!("{}mov{} {}, {}\n", divide, q, dx, ax),
+        BinaryOp::ShiftLeft => format!("sal{} %cl, {}\n", q, ax),
+        BinaryOp::ShiftRight if signed => format!("sar{} %cl, {}\n", q, ax),
+        BinaryOp::ShiftRight => format!("shr{} %cl, {}\n", q, ax),
+        BinaryOp::BitAnd => format!("and{} {}, {}\n", q, cx, ax),
+        BinaryOp::BitOr => format!("or{} {}, {}\n", q, cx, ax),
+        BinaryOp::BitXor => format!("xor{} {}, {}\n", q, cx, ax),
+        BinaryOp::LogAnd | BinaryOp::LogOr | BinaryOp::Comma => unreachable!(), // handled by the caller
+        // comparisons give an int 0 or 1, nothing to cut down
+        _ => {
+            let set = match (op, signed) {
+                (BinaryOp::Equal, _) => "sete",
+                (BinaryOp::NotEq, _) => "setne",
+                (BinaryOp::Less, true) => "setl",
+                (BinaryOp::LessEq, true) => "setle",
+                (BinaryOp::Greater, true) => "setg",
+                (BinaryOp::GreaterEq, true) => "setge",
+                (BinaryOp::Less, false) => "setb",
+                (BinaryOp::LessEq, false) => "setbe",
+                (BinaryOp::Greater, false) => "seta",
+                _ => "setae",
+            };
+            let compare = format!("cmp{} {}, {}\n{} %al\nmovzbq %al, %rax\n", q, cx, ax, set);
+            return gen_int_binary(left, &left_conv, right, &right_conv, &compare);
+        }
+    };
+    let op_asm = format!("{}{}", op_asm, extend(&ty));
+    gen_int_binary(left, &left_conv, right, &right_conv, &op_asm)
+}
+
+// left is kept on the stack while right is computed, then left goes in %rax and right in %rcx
+fn gen_int_binary(left: &str, left_conv: &str, right: &str, right_conv: &str, op_asm: &str) -> String {
+    format!("{}{}\npushq %rax\n{}{}\nmovq %rax, %rcx\npopq %rax\n{}", left, left_conv, right, right_conv, op_asm)
+}
+
+// p + n, n + p and p - n move by n elements, p - q counts the elements in between.
+// pointers also compare with each other and with 0
+fn gen_pointer_arith(op: &BinaryOp, left_type: &CType, left: &str, right_type: &CType, right: &str) -> String {
+    let scale = |ty: &CType| match ty.pointee().map_or(1, |pointee| pointee.size()) {
+        1 => String::new(),
+        size => format!("imulq ${}, %rax\n", size),
+    };
+    let is_integer = |ty: &CType| !ty.is_pointer() && !ty.is_floating();
+
+    let (left, right, op_asm) = match op {
+        BinaryOp::Addition if left_type.is_pointer() && is_integer(right_type) => {
+            (left.to_string(), format!("{}{}", right, scale(left_type)), "addq %rcx, %rax\n".to_string())
+        }
+        BinaryOp::Addition if is_integer(left_type) && right_type.is_pointer() => {
+            (format!("{}{}", left, scale(right_type)), right.to_string(), "addq %rcx, %rax\n".to_string())
+        }
+        BinaryOp::Subtraction if left_type.is_pointer() && is_integer(right_type) => {
+            (left.to_string(), format!("{}{}", right, scale(left_type)), "subq %rcx, %rax\n".to_string())
+        }
+        BinaryOp::Subtraction if left_type == right_type => {
+            let size = left_type.pointee().map_or(1, |pointee| pointee.size());
+            (left.to_string(), right.to_string(), format!("subq %rcx, %rax\nmovq ${}, %rcx\ncqto\nidivq %rcx\n", size))
+        }
+        BinaryOp::Equal | BinaryOp::NotEq | BinaryOp::Less | BinaryOp::LessEq | BinaryOp::Greater | BinaryOp::GreaterEq
+            if !left_type.is_floating() && !right_type.is_floating() =>
+        {
+            let set = match op {
+                BinaryOp::Equal => "sete",
+                BinaryOp::NotEq => "setne",
+                BinaryOp::Less => "setb",
+                BinaryOp::LessEq => "setbe",
+                BinaryOp::Greater => "seta",
+                _ => "setae",
+            };
+            // addresses are unsigned
+            (left.to_string(), right.to_string(), format!("cmpq %rcx, %rax\n{} %al\nmovzbq %al, %rax\n", set))
+        }
+        _ => unreachable!("the semantic pass checked the operands"),
+    };
+    format!("{}\npushq %rax\n{}\nmovq %rax, %rcx\npopq %rax\n{}", left, right, op_asm)
+}
+
+// bytes of a string literal as the inside of a gas .string directive
+fn escape_asm_string(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &byte in bytes {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out
+}
+
+// "s" for float and "d" for double, as in addss / addsd
+fn float_suffix(ty: &CType) -> &'static str {
+    if *ty == CType::Float { "s" } else { "d" }
+}
+
+// reads a variable of type ty at address, widening integers to the 64 bits we keep them in.
+// the value of an array is where it starts
+fn load(ty: &CType, address: &str) -> String {
+    match ty {
+        // a struct value is handled through its address too
+        CType::Array(..) | CType::Struct(_) => format!("leaq {}, %rax", address),
+        CType::Float => format!("movss {}, %xmm0", address),
+        CType::Double => format!("movsd {}, %xmm0", address),
+        CType::Char => format!("movsbq {}, %rax", address),
+        CType::UChar => format!("movzbl {}, %eax", address),
+        CType::Short => format!("movswq {}, %rax", address),
+        CType::UShort => format!("movzwl {}, %eax", address),
+        CType::Int => format!("movslq {}, %rax", address),
+        CType::UInt => format!("movl {}, %eax", address),
+        _ => format!("movq {}, %rax", address),
+    }
+}
+
+// writes only as many bytes as the type has, a global int next to other data is 4 bytes
+fn store(ty: &CType, address: &str) -> String {
+    match ty {
+        // %rax points at the struct to copy from
+        CType::Struct(_) => format!("movq %rax, %rsi\nleaq {}, %rdi\nmovq ${}, %rcx\nrep movsb", address, ty.size()),
+        CType::Float => format!("movss %xmm0, {}", address),
+        CType::Double => format!("movsd %xmm0, {}", address),
+        CType::Char | CType::UChar => format!("movb %al, {}", address),
+        CType::Short | CType::UShort => format!("movw %ax, {}", address),
+        CType::Int | CType::UInt => format!("movl %eax, {}", address),
+        _ => format!("movq %rax, {}", address),
+    }
+}
+
+// a variable in .data, .bss, .rodata or .data.rel.ro
+fn emit_data(object: &DataObject, context: &mut CodeGenContext) -> String {
+    let zero = match &object.init {
+        None => true,
+        Some(pieces) => pieces.iter().all(|piece| matches!(piece.value, StaticInit::Value(value) if value.is_zero())),
+    };
+    let relocated = object.init.iter().flatten().any(|piece| matches!(piece.value, StaticInit::Address(..)));
+    let section = if object.read_only && relocated {
+        // the addresses are only known once the program is loaded, the loader writes them
+        ".section .data.rel.ro,\"aw\""
+    } else if object.read_only {
+        ".section .rodata"
+    } else if zero {
+        ".bss"
+    } else {
+        ".data"
+    };
+    let size = object.ty.size();
+    let mut out = format!("{}\n", section);
+    if object.global {
+        out.push_str(&format!(".globl {}\n", object.label));
+    }
+    out.push_str(&format!(".align {}\n{}:\n", object.ty.align(), object.label));
+    let mut position = 0;
+    if let (Some(pieces), false) = (&object.init, zero) {
+        for piece in pieces {
+            if piece.offset > position {
+                out.push_str(&format!(".zero {}\n", piece.offset - position));
+            }
+            out.push_str(&emit_scalar(&piece.ty, &piece.value, context));
+            position = piece.offset + piece.ty.size();
+        }
+    }
+    if position < size {
+        out.push_str(&format!(".zero {}\n", size - position));
+    }
+    out
+}
+
+fn emit_scalar(ty: &CType, value: &StaticInit, context: &mut CodeGenContext) -> String {
+    let directive = match ty.size() {
+        1 => ".byte",
+        2 => ".short",
+        4 => ".long",
+        _ => ".quad",
+    };
+    match value {
+        StaticInit::Address(address, offset) => {
+            let label = match address {
+                AddressOf::Object(label) => label.clone(),
+                AddressOf::StringLiteral(bytes) => context.intern_string(bytes),
+            };
+            match offset {
+                0 => format!(".quad {}\n", label),
+                _ => format!(".quad {}{:+}\n", label, offset),
+            }
+        }
+        StaticInit::Value(constant::Value::Float(value)) if *ty == CType::Float => format!(".long {}  # {}\n", (*value as f32).to_bits(), value),
+        StaticInit::Value(constant::Value::Float(value)) => format!(".quad {}  # {}\n", value.to_bits(), value),
+        StaticInit::Value(constant::Value::Int(value)) => format!("{} {}\n", directive, value),
+    }
+}
+
+// turns a value of type `from` (in %rax or %xmm0) into a value of type `to`
+fn convert(from: &CType, to: &CType) -> String {
+    if from == to || *to == CType::Void {
+        return String::new();
+    }
+    match (from.is_floating(), to.is_floating()) {
+        // a wider type of the same signedness, or a wider signed one, holds the value as it is
+        (false, false) if to.size() > from.size() && (to.is_signed() || !from.is_signed()) => String::new(),
+        (false, false) => extend(to).to_string(),
+        (false, true) => int_to_float(from, to),
+        (true, false) => format!("{}{}", float_to_int(from, to), extend(to)),
+        (true, true) if *to == CType::Double => "cvtss2sd %xmm0, %xmm0\n".to_string(),
+        (true, true) => "cvtsd2ss %xmm0, %xmm0\n".to_string(),
+    }
+}
+
+fn int_to_float(from: &CType, to: &CType) -> String {
+    let suffix = float_suffix(to);
+    if from.is_signed() || from.size() < 8 {
+        return format!("cvtsi2s{}q %rax, %xmm0\n", suffix);
+    }
+    // cvtsi2sd only knows signed values. for the top half of an unsigned 64 bit range, halve the
+    // value (keeping the lowest bit so rounding stays right), convert and double it again
+    let label_big = new_label("u2f_big");
+    let label_end = new_label("u2f_end");
+    format!(
+        "testq %rax, %rax\n\
+        js {big}\n\
+        cvtsi2s{s}q %rax, %xmm0\n\
+        jmp {end}\n\
+        {big}:\n\
+        movq %rax, %rcx\n\
+        shrq %rcx\n\
+        andl $1, %eax\n\
+        orq %rax, %rcx\n\
+        cvtsi2s{s}q %rcx, %xmm0\n\
+        adds{s} %xmm0, %xmm0\n\
+        {end}:\n",
+        big = label_big, end = label_end, s = suffix
+    )
+}
+
+fn float_to_int(from: &CType, to: &CType) -> String {
+    let suffix = float_suffix(from);
+    if to.is_signed() || to.size() < 8 {
+        return format!("cvtts{}2siq %xmm0, %rax\n", suffix);
+    }
+    // values from 2^63 up don't fit the signed conversion: take 2^63 off first and put the top bit back after
+    let two_to_63 = if *from == CType::Float {
+        "movl $0x5f000000, %ecx\nmovd %ecx, %xmm1\n"
+    } else {
+        "movabsq $0x43e0000000000000, %rcx\nmovq %rcx, %xmm1\n"
+    };
+    let label_big = new_label("f2u_big");
+    let label_end = new_label("f2u_end");
+    format!(
+        "{two}\
+        ucomis{s} %xmm1, %xmm0\n\
+        jae {big}\n\
+        cvtts{s}2siq %xmm0, %rax\n\
+        jmp {end}\n\
+        {big}:\n\
+        subs{s} %xmm1, %xmm0\n\
+        cvtts{s}2siq %xmm0, %rax\n\
+        btcq $63, %rax\n\
+        {end}:\n",
+        two = two_to_63, big = label_big, end = label_end, s = suffix
+    )
+}
+
+// leaves 0 or 1 in %rax depending on whether the value is zero
+fn truth_value(ty: &CType) -> String {
+    if ty.is_floating() {
+        format!("xorps %xmm1, %xmm1\nucomis{} %xmm1, %xmm0\nsetne %al\nsetp %cl\norb %cl, %al\nmovzbq %al, %rax\n", float_suffix(ty))
+    } else {
+        String::new()
+    }
+}
+
+// arithmetic and comparisons once both sides are float or double: left ends up in %xmm0, right in %xmm1
+fn gen_float_binary(op: &BinaryOp, ty: &CType, left: &str, left_conv: &str, right: &str, right_conv: &str) -> String {
+    let s = float_suffix(ty);
+    let op_asm = match op {
+        BinaryOp::Addition => format!("adds{} %xmm1, %xmm0\n", s),
+        BinaryOp::Subtraction => format!("subs{} %xmm1, %xmm0\n", s),
+        BinaryOp::Multiplication => format!("muls{} %xmm1, %xmm0\n", s),
+        BinaryOp::Division => format!("divs{} %xmm1, %xmm0\n", s),
+        // ucomis sets the flags like an unsigned compare and sets PF for NaN. the operands of
+        // < and <= are swapped so that a NaN makes every ordered comparison false
+        BinaryOp::Equal => format!("ucomis{} %xmm1, %xmm0\nsete %al\nsetnp %cl\nandb %cl, %al\nmovzbq %al, %rax\n", s),
+        BinaryOp::NotEq => format!("ucomis{} %xmm1, %xmm0\nsetne %al\nsetp %cl\norb %cl, %al\nmovzbq %al, %rax\n", s),
+        BinaryOp::Less => format!("ucomis{} %xmm0, %xmm1\nseta %al\nmovzbq %al, %rax\n", s),
+        BinaryOp::LessEq => format!("ucomis{} %xmm0, %xmm1\nsetae %al\nmovzbq %al, %rax\n", s),
+        BinaryOp::Greater => format!("ucomis{} %xmm1, %xmm0\nseta %al\nmovzbq %al, %rax\n", s),
+        BinaryOp::GreaterEq => format!("ucomis{} %xmm1, %xmm0\nsetae %al\nmovzbq %al, %rax\n", s),
+        _ => unreachable!(), // integer only or handled by the caller
+    };
+    format!(
+        "{}{}subq $8, %rsp\nmovsd %xmm0, (%rsp)\n{}{}movaps %xmm0, %xmm1\nmovsd (%rsp), %xmm0\naddq $8, %rsp\n{}",
+        left, left_conv, right, right_conv, op_asm
+    )
+}
+
+
+static LABEL_COUNT: AtomicUsize = AtomicUsize::new(0);
+fn new_label(base: &str) -> String {
+    let count = LABEL_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    format!(".{}_{}", base, count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{semantic, Lexer::Lexer, Parser::Parser};
+
+    fn asm(source: &str) -> String {
+        let mut ast = Parser::new(Lexer::new(source)).parse().expect("the program parses");
+        let (data, _) = semantic::Checker::new().check(&mut ast).expect("the program checks");
+        genASm(&ast, &mut CodeGenContext::new(data)).expect("the program generates")
+    }
+
+    // the assembly of a function that switches over these case values
+    fn switch_asm(values: &[i64]) -> String {
+        let cases: String = values.iter().map(|value| format!("case {}: return 1; ", value)).collect();
+        asm(&format!("int f(int x) {{ switch (x) {{ {}}} return 0; }}", cases))
+    }
+
+    // the section directive right before a variable's label
+    fn section_of(asm: &str, name: &str) -> String {
+        let label = asm.find(&format!("\n{}:\n", name)).expect("the variable is emitted");
+        let sections = [".data", ".bss", ".section"];
+        let directive = asm[..label].lines().rev().find(|line| sections.iter().any(|section| line.starts_with(section)));
+        directive.expect("a section comes first").to_string()
+    }
+
+    #[test]
+    fn only_const_objects_are_read_only_and_addresses_are_relocated() {
+        let asm = asm("const char *s = \"a\"; char *const p = \"b\"; const int n = 1; const int *q = &n;");
+        assert_eq!(section_of(&asm, "s"), ".data");
+        assert_eq!(section_of(&asm, "p"), ".section .data.rel.ro,\"aw\"");
+        assert_eq!(section_of(&asm, "n"), ".section .rodata");
+        assert_eq!(section_of(&asm, "q"), ".data");
+    }
+
+    #[test]
+    fn few_cases_are_compared_one_by_one() {
+        let asm = switch_asm(&[1, 100, 10000]);
+        assert_eq!(asm.matches("je .case_").count(), 3);
+        assert!(!asm.contains(".switch_table"));
+        assert!(!asm.contains(".switch_lower"));
+    }
+
+    #[test]
+    fn dense_cases_use_a_jump_table() {
+        // 4 cases over a range of 12 is just dense enough
+        let asm = switch_asm(&[-5, 0, 3, 6]);
+        assert!(asm.contains("subq $-5, %rax\ncmpq $11, %rax\n"));
+        assert_eq!(asm.matches(".long ").count(), 12);
+        assert!(!asm.contains(".switch_lower"));
+    }
+
+    #[test]
+    fn sparse_cases_are_searched() {
+        // a range of 13 for 4 cases is too sparse for a table, as is a range over the table limit
+        for values in [&[0, 4, 8, 12][..], &[0, 5000, 5001, 5002, 5003, 5004]] {
+            let asm = switch_asm(values);
+            assert!(asm.contains(".switch_lower"));
+            assert!(!asm.contains(".switch_table"));
+        }
+    }
+
+    fn structure(members: &[CType]) -> CType {
+        let def = types::StructRef::declare(false, None);
+        def.define(members.iter().enumerate().map(|(index, ty)| (format!("m{}", index), ty.clone())).collect());
+        CType::Struct(def)
+    }
+
+    #[test]
+    fn arguments_past_the_registers_go_on_the_stack() {
+        use ArgLocation::{Register, Sse, Stack};
+        let longs = classify_args(&vec![CType::Long; 8], false);
+        let registers: Vec<Vec<ArgLocation>> = ARG_REGISTERS.iter().map(|register| vec![Register(register)]).collect();
+        assert_eq!(longs[..6], registers);
+        assert_eq!(longs[6..], [vec![Stack(0)], vec![Stack(1)]]);
+
+        let doubles = classify_args(&vec![CType::Double; 9], false);
+        let xmms: Vec<Vec<ArgLocation>> = (0..8).map(|xmm| vec![Sse(xmm)]).collect();
+        assert_eq!(doubles[..8], xmms);
+        assert_eq!(doubles[8], [Stack(0)]);
+
+        // the two kinds of register run out separately
+        let mixed = classify_args(&[CType::Int, CType::Double, CType::Int, CType::Float], false);
+        assert_eq!(mixed, [vec![Register("%rdi")], vec![Sse(0)], vec![Register("%rsi")], vec![Sse(1)]]);
+    }
+
+    #[test]
+    fn a_struct_is_in_registers_whole_or_on_the_stack_whole() {
+        use ArgLocation::{Register, Sse, Stack};
+        let mixed = structure(&[CType::Long, CType::Double]);
+        assert_eq!(classify_args(&[mixed], false), [vec![Register("%rdi"), Sse(0)]]);
+
+        // one register left isn't enough for two eightbytes, but the long after it still gets it
+        let pair = structure(&[CType::Long, CType::Long]);
+        let mut args = vec![CType::Long; 5];
+        args.extend([pair, CType::Long]);
+        let locations = classify_args(&args, false);
+        assert_eq!(locations[5], [Stack(0), Stack(1)]);
+        assert_eq!(locations[6], [Register("%r9")]);
+
+        let big = structure(&[CType::Long, CType::Long, CType::Long]);
+        assert_eq!(classify_args(&[big, CType::Int], false), [vec![Stack(0), Stack(1), Stack(2)], vec![Register("%rdi")]]);
+    }
+
+    #[test]
+    fn a_struct_returned_in_memory_takes_rdi() {
+        let big = structure(&[CType::Long, CType::Long, CType::Long]);
+        assert!(returns_in_memory(&big));
+        assert!(!returns_in_memory(&structure(&[CType::Long, CType::Double])));
+        assert!(!returns_in_memory(&CType::Long));
+        assert_eq!(classify_args(&[CType::Int], true), [vec![ArgLocation::Register("%rsi")]]);
+
+        let asm = asm("struct big { long a, b, c; }; struct big f(long x) { struct big v; v.a = x; return v; } \
+                       long g(void) { return f(1).a; }");
+        // the callee keeps the pointer, the caller passes one to its own slot
+        assert!(asm.contains("movq %rdi, ") && asm.contains("# Where the struct is returned"));
+        assert!(asm.contains("movq 0(%rax), %rsi\n"));
+        assert!(asm.contains(", %rdi\nmovl $0, %eax\ncall f@PLT"));
+    }
+}
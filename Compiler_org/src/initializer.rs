@@ -0,0 +1,162 @@
+use crate::Parser::{ASTNode, NodeKind};
+use crate::constant;
+use crate::diagnostics::Diagnostic;
+use crate::types::CType;
+
+// Initializers, taken apart into the scalars they set. The semantic pass checks them this way,
+// code generation stores them. For a variable with static storage the semantic pass also works
+// out the data it starts with, which code generation only has to write out.
+
+// one scalar an initializer sets, at its byte offset in the variable. int a[2][2] = {1, 2, 3}
+// sets a[0][0], a[0][1] and a[1][0], the rest is zero
+pub struct InitItem<'a> {
+    pub offset: u64,
+    pub ty: CType,
+    pub value: InitValue<'a>,
+}
+
+pub enum InitValue<'a> {
+    Expr(&'a ASTNode),
+    Byte(u8), // a char of a string literal initializing a char array
+}
+
+// a variable with static storage, emitted after the code
+pub struct DataObject {
+    pub label: String,
+    pub ty: CType,
+    pub init: Option<Vec<DataPiece>>, // None is all zero, .bss. bytes no piece covers are zero too
+    pub read_only: bool, // const goes in .rodata
+    pub global: bool,
+}
+
+// one scalar of the initial value of a variable in .data, at its byte offset in the variable
+#[derive(Clone)]
+pub struct DataPiece {
+    pub offset: u64,
+    pub ty: CType,
+    pub value: StaticInit,
+}
+
+// the value a scalar in .data starts with
+#[derive(Clone)]
+pub enum StaticInit {
+    Value(constant::Value),
+    Address(AddressOf, i64), // and a byte offset from it, for char *s = "..." or int *p = &a[2]
+}
+
+// what the linker puts the address of
+#[derive(Clone)]
+pub enum AddressOf {
+    Object(String), // by its label
+    StringLiteral(Vec<u8>),
+}
+
+
+// the scalars an initializer sets. braces may be left out inside: int a[2][2] = {1, 2, 3, 4}
+pub fn flatten_initializer<'a>(ty: &CType, init: &'a ASTNode, offset: u64, out: &mut Vec<InitItem<'a>>) -> Result<(), Diagnostic> {
+    match (ty, &init.kind) {
+        (CType::Array(element, count), NodeKind::StringLiteral(bytes)) if matches!(**element, CType::Char | CType::UChar) => {
+            // the terminating 0 is dropped when the array is exactly as long as the text
+            if bytes.len() as u64 > *count {
+                return Err(Diagnostic::error("initializer-string for array is too long", init.span).with_code("E0316"));
+            }
+            for (index, byte) in bytes.iter().enumerate() {
+                out.push(InitItem { offset: offset + index as u64, ty: element.as_ref().clone(), value: InitValue::Byte(*byte) });
+            }
+            Ok(())
+        }
+        (CType::Array(..) | CType::Struct(_), NodeKind::InitList(items)) => {
+            let mut items = items.iter().peekable();
+            fill_aggregate(ty, &mut items, offset, out)?;
+            match items.next() {
+                Some(extra) if ty.is_array() => Err(Diagnostic::error("excess elements in array initializer", extra.span).with_code("E0316")),
+                Some(extra) => Err(Diagnostic::error(format!("excess elements in {} initializer", ty), extra.span).with_code("E0316")),
+                None => Ok(()),
+            }
+        }
+        (CType::Array(..), _) => {
+            Err(Diagnostic::error(format!("invalid initializer for '{}'", ty), init.span).with_code("E0315"))
+        }
+        // int x = {1};
+        (_, NodeKind::InitList(items)) => match items.as_slice() {
+            [] => Ok(()),
+            [item] => flatten_initializer(ty, item, offset, out),
+            [_, extra, ..] => Err(Diagnostic::error("excess elements in scalar initializer", extra.span).with_code("E0316")),
+        },
+        // a scalar, or a whole struct copied from an expression
+        _ => {
+            out.push(InitItem { offset, ty: ty.clone(), value: InitValue::Expr(init) });
+            Ok(())
+        }
+    }
+}
+
+// the elements of an array or the members of a struct, in order, from a list. only the first
+// member of a union gets a value. gives back how many elements or members got one
+fn fill_aggregate<'a>(
+    ty: &CType,
+    items: &mut std::iter::Peekable<std::slice::Iter<'a, ASTNode>>,
+    offset: u64,
+    out: &mut Vec<InitItem<'a>>,
+) -> Result<u64, Diagnostic> {
+    let mut filled = 0;
+    match ty {
+        CType::Array(element, count) => {
+            while filled < *count && items.peek().is_some() {
+                fill_element(element, items, offset + filled * element.size(), out)?;
+                filled += 1;
+            }
+        }
+        CType::Struct(def) => {
+            let members = def.members();
+            let members = if def.is_union() { &members[..members.len().min(1)] } else { &members[..] };
+            for member in members {
+                if items.peek().is_none() {
+                    break;
+                }
+                fill_element(&member.ty, items, offset + member.offset, out)?;
+                filled += 1;
+            }
+        }
+        _ => unreachable!("only arrays and structs have elements"),
+    }
+    Ok(filled)
+}
+
+// one element of an aggregate. if it is an aggregate itself it takes its own { } if there is
+// one, otherwise as many items as it needs
+fn fill_element<'a>(
+    ty: &CType,
+    items: &mut std::iter::Peekable<std::slice::Iter<'a, ASTNode>>,
+    offset: u64,
+    out: &mut Vec<InitItem<'a>>,
+) -> Result<(), Diagnostic> {
+    let item = items.peek().expect("the caller checks there is an item left");
+    let own_initializer = match (ty, &item.kind) {
+        (_, NodeKind::InitList(_)) => true,
+        (CType::Array(element, _), NodeKind::StringLiteral(_)) => matches!(**element, CType::Char | CType::UChar),
+        (CType::Array(..) | CType::Struct(_), _) => false,
+        _ => true,
+    };
+    if own_initializer {
+        flatten_initializer(ty, items.next().unwrap(), offset, out)
+    } else {
+        fill_aggregate(ty, items, offset, out).map(|_| ())
+    }
+}
+
+// int a[] = {1, 2, 3} is an int[3]
+pub fn complete_type(ty: &CType, init: Option<&ASTNode>) -> CType {
+    let CType::Array(element, 0) = ty else {
+        return ty.clone();
+    };
+    let count = match init.map(|init| &init.kind) {
+        Some(NodeKind::InitList(items)) => {
+            let mut items = items.iter().peekable();
+            fill_aggregate(&CType::Array(element.clone(), u64::MAX), &mut items, 0, &mut Vec::new()).unwrap_or(0)
+        }
+        Some(NodeKind::StringLiteral(bytes)) if matches!(**element, CType::Char | CType::UChar) => bytes.len() as u64 + 1,
+        _ => 0,
+    };
+    CType::Array(element.clone(), count)
+}
@@ -1,34 +1,130 @@
+// module and function names follow the original naming of the project (Lexer, Parser, genASm)
+#![allow(non_snake_case, non_camel_case_types, clippy::upper_case_acronyms)]
+// errors are the cold path, a big Diagnostic in Err is fine
+#![allow(clippy::result_large_err)]
+
 mod generation;
+mod initializer;
+mod semantic;
+mod constant;
 mod Parser;
 mod Lexer;
+mod diagnostics;
+mod types;
 
 use std::fs::File;
-use std::io::{self, Read}; // Fix for missing imports
-use std::fmt::format;
-//use std::intrinsics::unreachable;
-use std::panic;
+use std::io::{self, IsTerminal, Read}; // Fix for missing imports
 use std::env;
+use std::process;
+use diagnostics::Diagnostic;
+
+#[derive(PartialEq)]
+enum DiagnosticsFormat {
+    Human,
+    Json,
+}
+
+struct Options {
+    file: String,
+    diagnostics_format: DiagnosticsFormat,
+    implicit_declarations: bool,
+}
+
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {} [--diagnostics-format=human|json] [--implicit-declarations] <filename>", program);
+    process::exit(1);
+}
+
+fn parse_args(args: &[String]) -> Options {
+    let mut file = None;
+    let mut diagnostics_format = DiagnosticsFormat::Human;
+    let mut implicit_declarations = false;
+    for arg in &args[1..] {
+        if let Some(format) = arg.strip_prefix("--diagnostics-format=") {
+            diagnostics_format = match format {
+                "human" => DiagnosticsFormat::Human,
+                "json" => DiagnosticsFormat::Json,
+                _ => {
+                    eprintln!("Unknown diagnostics format: {}", format);
+                    usage(&args[0]);
+                }
+            };
+        } else if arg == "--implicit-declarations" {
+            implicit_declarations = true;
+        } else if arg.starts_with("--") || file.is_some() {
+            eprintln!("Unexpected argument: {}", arg);
+            usage(&args[0]);
+        } else {
+            file = Some(arg.clone());
+        }
+    }
+    match file {
+        Some(file) => Options { file, diagnostics_format, implicit_declarations },
+        None => usage(&args[0]),
+    }
+}
 
 fn main() -> io::Result<()>
 {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <filename>", args[0]);
-        std::process::exit(1);
-    }
+    let options = parse_args(&args);
 
-    let file = &args[1];
+    let file = &options.file;
     let mut contents = String::new();
     
     let mut openedfile = File::open(file)?; 
 
     openedfile.read_to_string(&mut contents)?; 
-    let mut lexer_tokens = Lexer::Lexer::new(&contents);
+
+    match compile(&contents, &options) {
+        Ok((asm, warnings)) => {
+            for warning in &warnings {
+                report(&options, &contents, warning);
+            }
+            println!("{}", asm);
+        }
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                report(&options, &contents, diagnostic);
+            }
+            process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+fn report(options: &Options, contents: &str, diagnostic: &Diagnostic) {
+    match options.diagnostics_format {
+        // one object per line so tools can stream them
+        DiagnosticsFormat::Json => eprintln!("{}", diagnostic.to_json(&options.file, contents)),
+        DiagnosticsFormat::Human => {
+            // colors only make sense on a terminal, keep logs and pipes plain
+            let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+            eprint!("{}", diagnostic.render(&options.file, contents, color));
+        }
+    }
+}
+
+// source text in, assembly and warnings out. the parser reports every syntax error it finds and
+// the semantic pass every other error, code generation only runs on a program that is correct
+fn compile(contents: &str, options: &Options) -> Result<(String, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let lexer_tokens = Lexer::Lexer::new(contents);
     let mut parser = Parser::Parser::new(lexer_tokens);
 
-    let ast = parser.parse();
-    let mut context = generation::CodeGenContext::new();
+    let mut ast = parser.parse()?;
+    let mut checker = semantic::Checker::new();
+    if options.implicit_declarations {
+        checker.allow_implicit_declarations();
+    }
+    let (data, warnings) = checker.check(&mut ast)?;
+    let mut context = generation::CodeGenContext::new(data);
     //prettyPrinting(&ast);
-    println!("{}" , generation::genASm(&ast , &mut context));
-    Ok(())
-}
\ No newline at end of file
+    match generation::genASm(&ast , &mut context) {
+        Ok(asm) => Ok((asm, warnings)),
+        Err(error) => {
+            let mut diagnostics = warnings;
+            diagnostics.push(error);
+            Err(diagnostics)
+        }
+    }
+}
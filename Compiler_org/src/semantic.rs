@@ -0,0 +1,1257 @@
+use std::collections::HashMap;
+use crate::Parser::{ASTNode, BinaryOp, NodeKind, Param, Storage, UnaryOp};
+use crate::Lexer::Span;
+use crate::constant;
+use crate::diagnostics::Diagnostic;
+use crate::initializer::{self, AddressOf, DataObject, DataPiece, InitValue, StaticInit};
+use crate::types::{self, CType};
+
+// The checks between parsing and code generation. Every variable gets a name no other
+// declaration in the file has (x.3), every expression gets its type, and every error found is
+// collected, so code generation only ever sees a program that is correct. The variables with
+// static storage come out of it too, with the data they start with.
+
+// what a declared name stands for
+#[derive(Clone)]
+struct Symbol {
+    name: String, // as written, for messages
+    ty: CType,
+    decl_span: Span, // for "previous declaration here"
+    read_only: bool,
+    fixed_address: bool, // a global, a static or an extern: its address is a link time constant
+    function: bool, // ty is then what it returns, the rest is in the checker's functions
+}
+
+// a variable declared at file scope, possibly many times: int x; extern int x; int x = 1;
+struct GlobalInfo {
+    ty: CType,
+    decl_span: Span,
+    internal: bool, // static: the symbol isn't visible to other files
+    read_only: bool,
+    defined: bool, // some declaration wasn't extern, so this file owns the storage
+    init_span: Option<Span>, // the declaration with the initializer
+    init: Option<Vec<DataPiece>>,
+}
+
+// what a caller needs to know about a function
+struct FunctionSig {
+    params: Vec<CType>,
+    variadic: bool,
+    return_type: CType,
+    defined: bool, // we have seen its body
+    implicit: bool, // made up from a call, nothing is known about the parameters
+    unspecified: bool, // declared f(): no parameters, or with --implicit-declarations unknown ones as in C89
+    decl_span: Span,
+}
+
+impl FunctionSig {
+    fn display(&self, name: &str) -> String {
+        if self.implicit || self.unspecified {
+            return format!("{} {}()", self.return_type, name);
+        }
+        let mut params: Vec<String> = self.params.iter().map(|ty| ty.to_string()).collect();
+        if self.variadic {
+            params.push("...".to_string());
+        }
+        if params.is_empty() {
+            params.push("void".to_string());
+        }
+        format!("{} {}({})", self.return_type, name, params.join(", "))
+    }
+}
+
+// the labels of the switch being checked
+struct SwitchCases {
+    ty: Option<CType>, // the promoted type of the controlling expression, None if it had an error
+    values: Vec<(i64, Span)>, // the case values seen so far, converted to that type
+    default: Option<Span>,
+}
+
+pub struct Checker {
+    scopes: Vec<HashMap<String, String>>, // source name to unique name, file scope first, innermost last
+    symbols: HashMap<String, Symbol>, // by unique name
+    globals: HashMap<String, GlobalInfo>,
+    global_order: Vec<String>, // globals are emitted in the order they were first declared
+    statics: Vec<DataObject>, // static variables declared in a block
+    functions: HashMap<String, FunctionSig>, // the functions declared so far
+    return_type: CType, // of the function being checked
+    switches: Vec<SwitchCases>, // the switches we are in, innermost last
+    implicit_declarations: bool, // C89: calling an undeclared function declares it as int f()
+    diagnostics: Vec<Diagnostic>, // errors and warnings, in the order they were found
+    failed: bool,
+    renamed: usize,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            symbols: HashMap::new(),
+            globals: HashMap::new(),
+            global_order: Vec::new(),
+            statics: Vec::new(),
+            functions: HashMap::new(),
+            return_type: CType::Int,
+            switches: Vec::new(),
+            implicit_declarations: false,
+            diagnostics: Vec::new(),
+            failed: false,
+            renamed: 0,
+        }
+    }
+
+    pub fn allow_implicit_declarations(&mut self) {
+        self.implicit_declarations = true;
+    }
+
+    // checks the whole program, renaming and typing it in place. Ok has the variables with static
+    // storage and the warnings, Err everything found when there was at least one error
+    pub fn check(mut self, program: &mut ASTNode) -> Result<(Vec<DataObject>, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let NodeKind::Program(items) = &mut program.kind else {
+            unreachable!("the parser always gives back a program");
+        };
+        for item in items {
+            match item.kind {
+                NodeKind::Function { .. } => self.check_function(item),
+                NodeKind::Declare { .. } | NodeKind::DeclareList(_) => self.check_statement(item),
+                _ => {}
+            }
+        }
+        if self.failed {
+            return Err(self.diagnostics);
+        }
+        let mut data = Vec::new();
+        for name in self.global_order {
+            let global = self.globals.remove(&name).expect("every global in the order is declared");
+            if global.defined {
+                data.push(DataObject { label: name, ty: global.ty, init: global.init, read_only: global.read_only, global: !global.internal });
+            }
+        }
+        data.append(&mut self.statics);
+        Ok((data, self.diagnostics))
+    }
+
+    fn error(&mut self, error: Diagnostic) {
+        self.diagnostics.push(error);
+        self.failed = true;
+    }
+
+    // reports the error, for the `?` of an expression that can't be typed
+    fn fail<T>(&mut self, error: Diagnostic) -> Option<T> {
+        self.error(error);
+        None
+    }
+
+    // a local gets a name of its own, so a shadowed one can't be confused with it later
+    fn unique_name(&mut self, name: &str) -> String {
+        self.renamed += 1;
+        format!("{}.{}", name, self.renamed)
+    }
+
+    fn bind(&mut self, name: &str, unique: &str, symbol: Symbol) {
+        self.scopes.last_mut().expect("there is always file scope").insert(name.to_string(), unique.to_string());
+        self.symbols.insert(unique.to_string(), symbol);
+    }
+
+    // blocks from the inside out, then file scope
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    fn symbol(&self, unique: &str) -> &Symbol {
+        &self.symbols[unique]
+    }
+
+
+    fn check_function(&mut self, node: &mut ASTNode) {
+        let span = node.span;
+        let NodeKind::Function { name, params, variadic, prototyped, body, return_type, .. } = &mut node.kind else {
+            unreachable!();
+        };
+        // a function is known from its first declaration on, which lets it call itself
+        let sig = FunctionSig {
+            params: params.iter().map(|param| param.ty.clone()).collect(),
+            variadic: *variadic,
+            return_type: return_type.clone(),
+            defined: body.is_some(),
+            implicit: false,
+            unspecified: !*prototyped,
+            decl_span: span,
+        };
+        if let Err(error) = self.declare_function(name, sig) {
+            self.error(error);
+        }
+        let Some(body) = body else {
+            return;
+        };
+        self.return_type = return_type.clone();
+        // the parameters and the outermost block of the body share one scope
+        self.scopes.push(HashMap::new());
+        for param in params.iter_mut() {
+            self.check_param(param);
+        }
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_param(&mut self, param: &mut Param) {
+        let name = param.name.clone().expect("the parser makes sure the parameters of a definition have names");
+        if let Some(previous) = self.scopes.last().and_then(|scope| scope.get(&name)) {
+            let previous = self.symbol(previous).decl_span;
+            return self.error(Diagnostic::error(format!("redefinition of parameter '{}'", name), param.span)
+                .with_code("E0302")
+                .with_label(previous, "previous declaration here"));
+        }
+        if param.ty == CType::Void {
+            return self.error(Diagnostic::error(format!("parameter '{}' declared void", name), param.span).with_code("E0305"));
+        }
+        let unique = self.unique_name(&name);
+        let symbol = Symbol { name: name.clone(), ty: param.ty.clone(), decl_span: param.span, read_only: false, fixed_address: false, function: false };
+        self.bind(&name, &unique, symbol);
+        param.name = Some(unique);
+    }
+
+    // every declaration of a function has to agree with the ones before it, and only one has a body
+    fn declare_function(&mut self, name: &str, sig: FunctionSig) -> Result<(), Diagnostic> {
+        if let Some(global) = self.globals.get(name) {
+            return Err(other_kind_of_symbol(name, sig.decl_span, global.decl_span));
+        }
+        if let Some(previous) = self.functions.get(name) {
+            if sig.defined && previous.defined {
+                return Err(Diagnostic::error(format!("redefinition of '{}'", name), sig.decl_span)
+                    .with_code("E0307")
+                    .with_label(previous.decl_span, "previous definition here"));
+            }
+            let compatible = if previous.implicit {
+                sig.return_type == CType::Int
+            } else if self.unchecked(previous) || self.unchecked(&sig) {
+                // f() fits a prototype whose parameters are what the arguments get promoted to anyway
+                let known = if self.unchecked(&sig) { previous } else { &sig };
+                previous.return_type == sig.return_type
+                    && !known.variadic
+                    && known.params.iter().all(|ty| *ty != CType::Float && types::common_type(ty, ty) == *ty)
+            } else {
+                previous.params == sig.params && previous.variadic == sig.variadic && previous.return_type == sig.return_type
+            };
+            if !compatible {
+                let label = if previous.implicit { "implicit declaration here" } else { "previous declaration here" };
+                return Err(Diagnostic::error(format!("conflicting types for '{}'", name), sig.decl_span)
+                    .with_code("E0306")
+                    .with_label(previous.decl_span, label)
+                    .with_note(format!("'{}' does not match '{}'", sig.display(name), previous.display(name))));
+            }
+            // keep pointing at the first declaration, but remember a body once we saw one
+            let defined = sig.defined || previous.defined;
+            let decl_span = if sig.defined || previous.implicit { sig.decl_span } else { previous.decl_span };
+            // and whatever was said about the parameters, int f(); after int f(int); still takes an int
+            let sig = if self.unchecked(&sig) && !self.unchecked(previous) {
+                FunctionSig { params: previous.params.clone(), variadic: previous.variadic, unspecified: false, ..sig }
+            } else {
+                sig
+            };
+            self.functions.insert(name.to_string(), FunctionSig { defined, decl_span, ..sig });
+        } else {
+            // functions and file scope variables share one namespace
+            let symbol = Symbol { name: name.to_string(), ty: sig.return_type.clone(), decl_span: sig.decl_span, read_only: false, fixed_address: true, function: true };
+            self.scopes[0].insert(name.to_string(), name.to_string());
+            self.symbols.insert(name.to_string(), symbol);
+            self.functions.insert(name.to_string(), sig);
+        }
+        Ok(())
+    }
+
+
+    // calls to it can't be checked: it was made up from a call, or declared f() in C89
+    fn unchecked(&self, sig: &FunctionSig) -> bool {
+        sig.implicit || (sig.unspecified && self.implicit_declarations)
+    }
+
+    // a variable at file scope or in a block
+    fn check_declaration(&mut self, node: &mut ASTNode) {
+        let span = node.span;
+        let NodeKind::Declare { name, ty, storage, read_only, init } = &mut node.kind else {
+            unreachable!();
+        };
+        // int a[] = {1, 2, 3} is an int[3] from here on
+        *ty = initializer::complete_type(ty, init.as_deref());
+        // the variable is still declared, so its uses don't each say it isn't
+        if *ty == CType::Void {
+            self.error(Diagnostic::error(format!("variable '{}' declared void", name), span).with_code("E0305"));
+        } else if *storage != Storage::Extern {
+            if let CType::Array(_, 0) = ty {
+                self.error(Diagnostic::error(format!("array size missing in '{}'", name), span).with_code("E0317"));
+            }
+            if let CType::Struct(def) = &ty {
+                if !def.is_complete() {
+                    self.error(Diagnostic::error(format!("storage size of '{}' isn't known", name), span).with_code("E0320"));
+                }
+            }
+        }
+
+        // the name is in scope right after its declarator, so the initializer sees it: void *p = &p;
+        if self.scopes.len() == 1 {
+            if let Err(error) = self.declare_global(name, ty, *storage, *read_only, init.is_some(), span) {
+                self.error(error);
+            }
+            if let Some(init) = init {
+                let data = self.check_initializer(ty, init, true);
+                if let (Some(data), Some(global)) = (data, self.globals.get_mut(name.as_str())) {
+                    global.init = Some(data);
+                }
+            }
+            return;
+        }
+        if let Some(previous) = self.scopes.last().and_then(|scope| scope.get(name.as_str())) {
+            let previous = self.symbol(previous).decl_span;
+            return self.error(Diagnostic::error("Redeclaration. invalid", span)
+                .with_code("E0302")
+                .with_label(previous, "previous declaration here")
+                .with_note(format!("'{}' is already declared in this block", name)));
+        }
+        match storage {
+            // the global itself, seen from inside the block
+            Storage::Extern => {
+                if init.is_some() {
+                    self.error(Diagnostic::error(format!("'{}' has both 'extern' and an initializer", name), span).with_code("E0314"));
+                }
+                if let Err(error) = self.declare_global(name, ty, Storage::Extern, *read_only, false, span) {
+                    return self.error(error);
+                }
+                let global = name.clone();
+                self.scopes.last_mut().expect("in a block").insert(global.clone(), global);
+            }
+            Storage::Static | Storage::Default => {
+                let unique = self.unique_name(name);
+                let symbol = Symbol { name: name.clone(), ty: ty.clone(), decl_span: span, read_only: *read_only, fixed_address: *storage == Storage::Static, function: false };
+                self.bind(name, &unique, symbol);
+                *name = unique;
+                let data = init.as_deref_mut().and_then(|init| self.check_initializer(ty, init, *storage == Storage::Static));
+                // lives in .data under its unique name, so two functions can both have a static x
+                if *storage == Storage::Static {
+                    self.statics.push(DataObject { label: name.clone(), ty: ty.clone(), init: data, read_only: *read_only, global: false });
+                }
+            }
+        }
+    }
+
+    // a file scope declaration. there can be many for one variable as long as they agree and at
+    // most one has an initializer. an extern in a block declares the same variable
+    fn declare_global(&mut self, name: &str, ty: &CType, storage: Storage, read_only: bool, has_init: bool, span: Span) -> Result<(), Diagnostic> {
+        if let Some(function) = self.functions.get(name) {
+            return Err(other_kind_of_symbol(name, span, function.decl_span));
+        }
+        let defined = storage != Storage::Extern || has_init;
+        let Some(previous) = self.globals.get_mut(name) else {
+            self.globals.insert(name.to_string(), GlobalInfo {
+                ty: ty.clone(),
+                decl_span: span,
+                internal: storage == Storage::Static,
+                read_only,
+                defined,
+                init_span: has_init.then_some(span),
+                init: None,
+            });
+            self.global_order.push(name.to_string());
+            let symbol = Symbol { name: name.to_string(), ty: ty.clone(), decl_span: span, read_only, fixed_address: true, function: false };
+            self.scopes[0].insert(name.to_string(), name.to_string());
+            self.symbols.insert(name.to_string(), symbol);
+            return Ok(());
+        };
+
+        // extern int a[]; and int a[3]; are the same array, the size comes from whichever has it
+        let same_array = match (&previous.ty, ty) {
+            (CType::Array(element, 0), CType::Array(other, _)) | (CType::Array(element, _), CType::Array(other, 0)) => element == other,
+            _ => false,
+        };
+        if same_array {
+            if let CType::Array(_, 0) = previous.ty {
+                previous.ty = ty.clone();
+            }
+        } else if previous.ty != *ty || previous.read_only != read_only {
+            let qualifier = |read_only: bool| if read_only { "const " } else { "" };
+            return Err(Diagnostic::error(format!("conflicting types for '{}'", name), span)
+                .with_code("E0306")
+                .with_label(previous.decl_span, "previous declaration here")
+                .with_note(format!("'{}{}' does not match '{}{}'", qualifier(read_only), ty, qualifier(previous.read_only), previous.ty)));
+        }
+        if let (true, Some(previous_init)) = (has_init, previous.init_span) {
+            return Err(Diagnostic::error(format!("redefinition of '{}'", name), span)
+                .with_code("E0307")
+                .with_label(previous_init, "previous definition here"));
+        }
+        // extern takes whatever linkage the variable already has
+        if storage != Storage::Extern && previous.internal != (storage == Storage::Static) {
+            let message = if previous.internal {
+                format!("non-static declaration of '{}' follows static declaration", name)
+            } else {
+                format!("static declaration of '{}' follows non-static declaration", name)
+            };
+            return Err(Diagnostic::error(message, span).with_code("E0312").with_label(previous.decl_span, "previous declaration here"));
+        }
+        if has_init {
+            previous.init_span = Some(span);
+        }
+        previous.defined |= defined;
+        let ty = previous.ty.clone();
+        if let Some(symbol) = self.symbols.get_mut(name) {
+            symbol.ty = ty;
+        }
+        Ok(())
+    }
+
+    // types every expression of the initializer, then checks each value against what it sets.
+    // with static storage the values have to be known before the program runs, and they come
+    // back as the data the variable starts with
+    fn check_initializer(&mut self, ty: &CType, init: &mut ASTNode, constant: bool) -> Option<Vec<DataPiece>> {
+        if !self.check_init_expressions(init) {
+            return None;
+        }
+        let mut items = Vec::new();
+        if let Err(error) = initializer::flatten_initializer(ty, init, 0, &mut items) {
+            return self.fail(error);
+        }
+        let mut errors = Vec::new();
+        let mut data = Vec::new();
+        for item in items {
+            let value = match item.value {
+                InitValue::Expr(expr) => {
+                    if let Err(error) = check_assignable(&item.ty, &value_type(expr), expr) {
+                        errors.push(error);
+                        continue;
+                    }
+                    if !constant {
+                        continue;
+                    }
+                    let Some(value) = self.static_value(&item.ty, expr) else {
+                        errors.push(Diagnostic::error("initializer element is not constant", expr.span).with_code("E0311"));
+                        continue;
+                    };
+                    value
+                }
+                InitValue::Byte(byte) => StaticInit::Value(constant::Value::Int(byte as i64)),
+            };
+            data.push(DataPiece { offset: item.offset, ty: item.ty, value });
+        }
+        let ok = errors.is_empty();
+        for error in errors {
+            self.error(error);
+        }
+        ok.then_some(data)
+    }
+
+    fn check_init_expressions(&mut self, init: &mut ASTNode) -> bool {
+        match &mut init.kind {
+            NodeKind::InitList(items) => {
+                // every item is checked so that all of their errors are reported
+                let mut ok = true;
+                for item in items.iter_mut() {
+                    ok &= self.check_init_expressions(item);
+                }
+                ok
+            }
+            _ => self.check_value(init).is_some(),
+        }
+    }
+
+    // a number, or an address the linker can work out
+    fn static_value(&self, ty: &CType, expr: &ASTNode) -> Option<StaticInit> {
+        if let Some((address, offset)) = self.static_pointer(expr) {
+            return Some(StaticInit::Address(address, offset));
+        }
+        constant::eval(expr).map(|(value, _)| StaticInit::Value(value.convert(ty)))
+    }
+
+    // a pointer to something with a fixed address and a byte offset from it: "abc", &x, arr + 2, &m[1][0]
+    fn static_pointer(&self, expr: &ASTNode) -> Option<(AddressOf, i64)> {
+        match &expr.kind {
+            NodeKind::StringLiteral(bytes) => Some((AddressOf::StringLiteral(bytes.clone()), 0)),
+            NodeKind::UnaryOp(UnaryOp::AddressOf, target) => self.static_address(target),
+            NodeKind::Cast(ty, inner) if ty.is_pointer() => self.static_pointer(inner),
+            NodeKind::BinaryOp(left, op @ (BinaryOp::Addition | BinaryOp::Subtraction), right) => {
+                let (pointer, index, sign) = match (value_type(left).is_pointer(), op) {
+                    (true, BinaryOp::Addition) => (left, right, 1),
+                    (true, _) => (left, right, -1),
+                    (false, BinaryOp::Addition) => (right, left, 1),
+                    (false, _) => return None,
+                };
+                let size = value_type(pointer).pointee().map_or(1, |pointee| pointee.size()) as i64;
+                let (address, offset) = self.static_pointer(pointer)?;
+                let Some((constant::Value::Int(index), _)) = constant::eval(index) else {
+                    return None;
+                };
+                Some((address, offset + sign * index * size))
+            }
+            // an array that decays to a pointer
+            _ if object_type(expr).is_array() => self.static_address(expr),
+            _ => None,
+        }
+    }
+
+    // where an lvalue with static storage is
+    fn static_address(&self, lvalue: &ASTNode) -> Option<(AddressOf, i64)> {
+        match &lvalue.kind {
+            NodeKind::Var(name) if self.symbol(name).fixed_address => Some((AddressOf::Object(name.clone()), 0)),
+            NodeKind::UnaryOp(UnaryOp::Deref, pointer) => self.static_pointer(pointer),
+            _ => None,
+        }
+    }
+
+
+    fn check_statement(&mut self, node: &mut ASTNode) {
+        if let NodeKind::Declare { .. } = node.kind {
+            return self.check_declaration(node);
+        }
+        let span = node.span;
+        match &mut node.kind {
+            // a void function returns nothing, or the nothing another void function gives back
+            NodeKind::Return(None) if self.return_type != CType::Void => {
+                self.error(Diagnostic::error(format!("'return' with no value, in function returning '{}'", self.return_type), span)
+                    .with_code("E0325"));
+            }
+            NodeKind::Return(None) => {}
+            NodeKind::Return(Some(value)) if self.return_type == CType::Void => {
+                if self.check_expression(value).is_some_and(|ty| ty != CType::Void) {
+                    self.error(Diagnostic::error("'return' with a value, in function returning void", value.span).with_code("E0325"));
+                }
+            }
+            NodeKind::Return(Some(value)) => {
+                if let Some(ty) = self.check_value(value) {
+                    if let Err(error) = check_assignable(&self.return_type, &ty, value) {
+                        self.error(error);
+                    }
+                }
+            }
+            NodeKind::Exp(expr) => {
+                self.check_expression(expr);
+            }
+            NodeKind::Conditional(condition, then_body, else_body) => {
+                self.check_scalar(condition);
+                self.check_block(then_body);
+                if let Some(else_body) = else_body {
+                    self.check_block(else_body);
+                }
+            }
+            NodeKind::While(condition, body) | NodeKind::DoWhile(body, condition) => {
+                self.check_scalar(condition);
+                self.check_block(body);
+            }
+            // a variable declared in init lives in a scope around the loop
+            NodeKind::For { init, condition, step, body } => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = init {
+                    self.check_statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.check_scalar(condition);
+                }
+                if let Some(step) = step {
+                    self.check_expression(step);
+                }
+                self.check_block(body);
+                self.scopes.pop();
+            }
+            NodeKind::Block(body) => self.check_block(body),
+            NodeKind::DeclareList(declarations) => {
+                for declaration in declarations {
+                    self.check_declaration(declaration);
+                }
+            }
+            NodeKind::Switch(value, body) => {
+                let ty = match self.check_value(value) {
+                    Some(ty) if ty.is_integer() => Some(types::common_type(&ty, &ty)),
+                    Some(ty) => {
+                        self.error(Diagnostic::error(format!("switch quantity not an integer (have '{}')", ty), value.span).with_code("E0321"));
+                        None
+                    }
+                    None => None,
+                };
+                self.switches.push(SwitchCases { ty, values: Vec::new(), default: None });
+                self.check_block(body);
+                self.switches.pop();
+            }
+            NodeKind::Case(value, statement) => {
+                self.check_case(value);
+                self.check_statement(statement);
+            }
+            NodeKind::Default(statement) => {
+                let switch = self.switches.last_mut().expect("the parser rejects default outside a switch");
+                match switch.default {
+                    Some(previous) => self.error(
+                        Diagnostic::error("multiple default labels in one switch", span)
+                            .with_code("E0322")
+                            .with_label(previous, "previous default here"),
+                    ),
+                    None => switch.default = Some(span),
+                }
+                self.check_statement(statement);
+            }
+            NodeKind::Break | NodeKind::Continue | NodeKind::Empty => {}
+            _ => unreachable!("the parser wraps an expression statement in Exp"),
+        }
+    }
+
+    // the value of a case label converted to the type of its switch, which is what gets compared.
+    // it is written back as a constant of that type, so code generation just reads it
+    fn check_case(&mut self, value: &mut ASTNode) {
+        self.check_expression(value);
+        let switch = self.switches.last_mut().expect("the parser rejects case outside a switch");
+        let Some(ty) = switch.ty.clone() else {
+            return;
+        };
+        let converted = match constant::eval(value).map(|(constant, _)| constant.convert(&ty)) {
+            Some(constant::Value::Int(converted)) => converted,
+            _ => unreachable!("the parser only accepts an integer constant as a case value"),
+        };
+        if let Some((_, previous)) = switch.values.iter().find(|(other, _)| *other == converted) {
+            let error = Diagnostic::error("duplicate case value", value.span).with_code("E0322").with_label(*previous, "previously used here");
+            return self.error(error);
+        }
+        switch.values.push((converted, value.span));
+        *value = ASTNode { kind: NodeKind::Constant(converted, ty.clone()), span: value.span, ty: Some(ty) };
+    }
+
+    // statements in a scope of their own
+    fn check_block(&mut self, body: &mut [ASTNode]) {
+        self.scopes.push(HashMap::new());
+        for stmt in body {
+            self.check_statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+
+    // types the expression and everything in it. None when there was an error in it, which is
+    // already reported: an expression using a broken one doesn't report it again
+    fn check_expression(&mut self, node: &mut ASTNode) -> Option<CType> {
+        let ty = self.expression_type(node)?;
+        node.ty = Some(ty);
+        Some(value_type(node))
+    }
+
+    // check_expression for an expression whose value is used: an operand, a condition, an argument,
+    // what is assigned. a call of a void function has no value to use
+    fn check_value(&mut self, node: &mut ASTNode) -> Option<CType> {
+        let ty = self.check_expression(node)?;
+        if ty == CType::Void {
+            return self.fail(Diagnostic::error("void value not ignored as it ought to be", node.span).with_code("E0323"));
+        }
+        Some(ty)
+    }
+
+    // check_value for what is compared with 0: a condition and the operands of !, && and ||
+    fn check_scalar(&mut self, node: &mut ASTNode) -> Option<CType> {
+        let ty = self.check_value(node)?;
+        if ty.is_struct() {
+            return self.fail(Diagnostic::error(format!("used '{}' value where a scalar is required", ty), node.span).with_code("E0304"));
+        }
+        Some(ty)
+    }
+
+    // the type of node, before an array decays
+    fn expression_type(&mut self, node: &mut ASTNode) -> Option<CType> {
+        let span = node.span;
+        match &mut node.kind {
+            NodeKind::Constant(_, ty) | NodeKind::FloatConstant(_, ty) => Some(ty.clone()),
+            NodeKind::StringLiteral(_) => Some(CType::Char.pointer_to()),
+            NodeKind::Var(name) => {
+                let Some(unique) = self.resolve(name) else {
+                    return self.fail(Diagnostic::error(format!("Variable {} not found", name), span).with_code("E0301"));
+                };
+                *name = unique;
+                if self.symbol(name).function {
+                    return self.fail(Diagnostic::error(format!("function '{}' used as a value", name), span)
+                        .with_code("E0327")
+                        .with_note("functions can only be called"));
+                }
+                Some(self.symbol(name).ty.clone())
+            }
+            // anything can be cast to void to drop it. otherwise only scalars convert, and a pointer
+            // only to and from an integer or another pointer
+            NodeKind::Cast(ty, expr) => {
+                if *ty == CType::Void {
+                    self.check_expression(expr)?;
+                    return Some(CType::Void);
+                }
+                let from = self.check_value(expr)?;
+                let problem = if ty.is_struct() {
+                    Some("conversion to non-scalar type requested")
+                } else if from.is_struct() {
+                    Some("aggregate value used where a scalar was expected")
+                } else if (ty.is_pointer() && from.is_floating()) || (ty.is_floating() && from.is_pointer()) {
+                    Some("a pointer can't be converted to or from a floating type")
+                } else {
+                    None
+                };
+                if let Some(problem) = problem {
+                    return self.fail(Diagnostic::error(format!("invalid cast from '{}' to '{}'", from, ty), span)
+                        .with_code("E0324")
+                        .with_note(problem));
+                }
+                Some(ty.clone())
+            }
+            NodeKind::Assign(target, value) => {
+                let target_type = self.check_lvalue(target, "left operand of assignment");
+                let value_type = self.check_value(value);
+                let (target_type, value_type) = (target_type?, value_type?);
+                if let Err(error) = check_assignable(&target_type, &value_type, value) {
+                    return self.fail(error);
+                }
+                Some(target_type)
+            }
+            // a op= e is a = a op e
+            NodeKind::CompoundAssign(target, op, value) => {
+                let target_type = self.check_lvalue(target, "left operand of assignment");
+                let value_type = self.check_value(value);
+                let (target_type, value_type) = (target_type?, value_type?);
+                self.check_binary(op, &target_type, &value_type, span)?;
+                Some(target_type)
+            }
+            NodeKind::Call(name, args) => {
+                let mut arg_types = Vec::new();
+                for arg in args.iter_mut() {
+                    arg_types.push(self.check_value(arg));
+                }
+                self.check_call(name, args, &arg_types, span)
+            }
+            // the comma operator is the one place a void operand is fine: f(), 1
+            NodeKind::BinaryOp(left, BinaryOp::Comma, right) => {
+                let left_type = self.check_expression(left);
+                let right_type = self.check_expression(right);
+                left_type?;
+                right_type
+            }
+            // && and || look at each operand on its own
+            NodeKind::BinaryOp(left, BinaryOp::LogAnd | BinaryOp::LogOr, right) => {
+                let left_type = self.check_scalar(left);
+                let right_type = self.check_scalar(right);
+                left_type?;
+                right_type?;
+                Some(CType::Int)
+            }
+            NodeKind::BinaryOp(left, op, right) => {
+                let left_type = self.check_value(left);
+                let right_type = self.check_value(right);
+                let (left_type, right_type) = (left_type?, right_type?);
+                self.check_binary(op, &left_type, &right_type, span)
+            }
+            NodeKind::UnaryOp(UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement, expr) => {
+                let ty = self.check_lvalue(expr, "increment or decrement operand")?;
+                if ty.is_struct() {
+                    return self.fail(Diagnostic::error(format!("wrong type argument to increment or decrement (have '{}')", ty), span).with_code("E0304"));
+                }
+                Some(ty)
+            }
+            NodeKind::UnaryOp(UnaryOp::AddressOf, expr) => {
+                if !is_lvalue(expr) {
+                    self.check_expression(expr)?;
+                    return self.fail(Diagnostic::error("lvalue required as unary '&' operand", expr.span).with_code("E0209"));
+                }
+                self.check_expression(expr)?;
+                Some(object_type(expr).pointer_to())
+            }
+            // void * can't be dereferenced, there is no value behind it
+            NodeKind::UnaryOp(UnaryOp::Deref, pointer) => {
+                let ty = self.check_value(pointer)?;
+                match ty.pointee() {
+                    Some(CType::Void) => self.fail(Diagnostic::error("dereferencing 'void *' pointer", span).with_code("E0304")),
+                    Some(pointee) => Some(pointee.clone()),
+                    None => self.fail(Diagnostic::error(format!("invalid type argument of unary '*' (have '{}')", ty), span).with_code("E0304")),
+                }
+            }
+            NodeKind::UnaryOp(UnaryOp::Not, expr) => {
+                self.check_scalar(expr)?;
+                Some(CType::Int)
+            }
+            NodeKind::UnaryOp(op, expr) => {
+                let ty = self.check_value(expr)?;
+                if ty.is_pointer() || ty.is_struct() {
+                    return self.fail(Diagnostic::error(format!("wrong type argument to unary operator (have '{}')", ty), span).with_code("E0304"));
+                }
+                if ty.is_floating() && matches!(op, UnaryOp::BitNot) {
+                    return self.fail(Diagnostic::error(format!("invalid operand to '~' (have '{}')", ty), span).with_code("E0304"));
+                }
+                // - and ~ work on the promoted operand
+                Some(types::common_type(&ty, &ty))
+            }
+            NodeKind::Member(base, name) => {
+                let ty = self.check_expression(base)?;
+                let CType::Struct(def) = &ty else {
+                    return self.fail(Diagnostic::error(format!("request for member '{}' in something not a structure or union (have '{}')", name, ty), span)
+                        .with_code("E0319"));
+                };
+                if !def.is_complete() {
+                    return self.fail(Diagnostic::error(format!("invalid use of incomplete type '{}'", ty), span).with_code("E0320"));
+                }
+                match def.member(name) {
+                    Some(member) => Some(member.ty),
+                    None => self.fail(Diagnostic::error(format!("'{}' has no member named '{}'", ty, name), span).with_code("E0318")),
+                }
+            }
+            // the arms are both numbers, both void, the same struct, or pointers one of which could
+            // be assigned the other. a void * arm makes the result a void * too
+            NodeKind::CondExp(condition, if_expr, else_expr) => {
+                let condition = self.check_scalar(condition);
+                let if_type = self.check_expression(if_expr);
+                let else_type = self.check_expression(else_expr);
+                let (_, if_type, else_type) = (condition?, if_type?, else_type?);
+                let ty = match (if_type.pointee(), else_type.pointee()) {
+                    (Some(CType::Void), Some(_)) => Some(if_type.clone()),
+                    (Some(_), Some(CType::Void)) => Some(else_type.clone()),
+                    (Some(left), Some(right)) => (left == right).then(|| if_type.clone()),
+                    (Some(_), None) => is_null_pointer(&else_type, else_expr).then(|| if_type.clone()),
+                    (None, Some(_)) => is_null_pointer(&if_type, if_expr).then(|| else_type.clone()),
+                    (None, None) if if_type.is_arithmetic() && else_type.is_arithmetic() => Some(types::common_type(&if_type, &else_type)),
+                    (None, None) => (if_type == else_type && (if_type.is_struct() || if_type == CType::Void)).then(|| if_type.clone()),
+                };
+                match ty {
+                    Some(ty) => Some(ty),
+                    None => self.fail(Diagnostic::error(format!("type mismatch in conditional expression (have '{}' and '{}')", if_type, else_type), span)
+                        .with_code("E0304")),
+                }
+            }
+            _ => unreachable!("only expressions have a type"),
+        }
+    }
+
+    // an assignment, ++ or -- writes to the object the expression names: a variable, *pointer
+    // or a member. not a whole array, and not something declared const
+    fn check_lvalue(&mut self, node: &mut ASTNode, what: &str) -> Option<CType> {
+        if !is_lvalue(node) {
+            self.check_expression(node)?;
+            return self.fail(Diagnostic::error(format!("lvalue required as {}", what), node.span).with_code("E0209"));
+        }
+        self.check_expression(node)?;
+        let ty = object_type(node);
+        if ty.is_array() {
+            return self.fail(Diagnostic::error("assignment to expression with array type", node.span).with_code("E0209"));
+        }
+        // s.x = 1 writes to s
+        let mut written = &*node;
+        while let NodeKind::Member(base, _) = &written.kind {
+            written = base;
+        }
+        if let NodeKind::Var(name) = &written.kind {
+            let symbol = self.symbol(name);
+            if symbol.read_only {
+                let error = Diagnostic::error(format!("assignment of read-only variable '{}'", symbol.name), node.span)
+                    .with_code("E0313")
+                    .with_label(symbol.decl_span, "declared const here");
+                return self.fail(error);
+            }
+        }
+        Some(ty)
+    }
+
+    // the operands of an arithmetic, bitwise or comparison operator, and the type of the result
+    fn check_binary(&mut self, op: &BinaryOp, left: &CType, right: &CType, span: Span) -> Option<CType> {
+        let integer_only = matches!(op, BinaryOp::Modulo | BinaryOp::ShiftLeft | BinaryOp::ShiftRight | BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor);
+        let valid = if left.is_struct() || right.is_struct() || (integer_only && (left.is_floating() || right.is_floating())) {
+            false
+        } else if left.is_pointer() || right.is_pointer() {
+            valid_pointer_operands(op, left, right)
+        } else {
+            true
+        };
+        if !valid {
+            return self.fail(Diagnostic::error(
+                format!("invalid operands to binary {} (have '{}' and '{}')", binary_symbol(op), left, right),
+                span,
+            ).with_code("E0304"));
+        }
+        Some(binary_type(op, left, right))
+    }
+
+    // a call has to match what the function was declared with. an undeclared function is an error,
+    // or with implicit declarations allowed a warning and an int f() declaration, like C89 did
+    fn check_call(&mut self, name: &str, args: &[ASTNode], arg_types: &[Option<CType>], span: Span) -> Option<CType> {
+        // the name is looked up like any other, a variable hides a function
+        if let Some(unique) = self.resolve(name) {
+            let symbol = self.symbol(&unique);
+            if !symbol.function {
+                let error = Diagnostic::error(format!("called object '{}' is not a function (have '{}')", name, symbol.ty), span)
+                    .with_code("E0326")
+                    .with_label(symbol.decl_span, "declared here");
+                return self.fail(error);
+            }
+        }
+        let Some(sig) = self.functions.get(name) else {
+            if !self.implicit_declarations {
+                return self.fail(Diagnostic::error(format!("call to undeclared function '{}'", name), span)
+                    .with_code("E0308")
+                    .with_note(format!("declare it before the call, e.g. 'int {}(int);'", name))
+                    .with_note("--implicit-declarations accepts this the way C89 did"));
+            }
+            self.diagnostics.push(Diagnostic::warning(format!("implicit declaration of function '{}'", name), span)
+                .with_code("W0301"));
+            let sig = FunctionSig { params: Vec::new(), variadic: true, return_type: CType::Int, defined: false, implicit: true, unspecified: false, decl_span: span };
+            self.declare_function(name, sig).expect("the name isn't declared");
+            return arg_types.iter().all(Option::is_some).then_some(CType::Int);
+        };
+        let return_type = sig.return_type.clone();
+        if self.unchecked(sig) {
+            return arg_types.iter().all(Option::is_some).then_some(return_type);
+        }
+
+        let problem = if args.len() < sig.params.len() {
+            Some("too few")
+        } else if args.len() > sig.params.len() && !sig.variadic {
+            Some("too many")
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            let mut error = Diagnostic::error(format!("{} arguments to function '{}'", problem, name), span)
+                .with_code("E0309")
+                .with_label(sig.decl_span, format!("declared here as '{}'", sig.display(name)))
+                .with_note(format!("expected {}{}, found {}", sig.params.len(), if sig.variadic { " or more" } else { "" }, args.len()));
+            if sig.unspecified {
+                error = error.with_note(format!(
+                    "'{}()' takes no parameters, like '{}(void)'. list them in the declaration, or use --implicit-declarations for the C89 meaning of '()'",
+                    name, name
+                ));
+            }
+            return self.fail(error);
+        }
+
+        // an argument is assigned to its parameter
+        let mut errors = Vec::new();
+        for (index, (arg, param_type)) in args.iter().zip(&sig.params).enumerate() {
+            let Some(arg_type) = &arg_types[index] else {
+                continue;
+            };
+            if check_assignable(param_type, arg_type, arg).is_err() {
+                errors.push(Diagnostic::error(format!("incompatible type for argument {} of '{}'", index + 1, name), arg.span)
+                    .with_code("E0310")
+                    .with_label(sig.decl_span, format!("declared here as '{}'", sig.display(name)))
+                    .with_note(format!("expected '{}' but argument is of type '{}'", param_type, arg_type)));
+            }
+        }
+        let ok = errors.is_empty() && arg_types.iter().all(Option::is_some);
+        for error in errors {
+            self.error(error);
+        }
+        ok.then_some(return_type)
+    }
+}
+
+// the type an expression has once checked, before an array decays. &a of int a[3] is an int (*)[3]
+pub fn object_type(node: &ASTNode) -> CType {
+    node.ty.clone().expect("the semantic pass types every expression")
+}
+
+// the type of the value an expression gives: an array is the address of its first element
+pub fn value_type(node: &ASTNode) -> CType {
+    object_type(node).decay()
+}
+
+// the type of `left op right`
+pub fn binary_type(op: &BinaryOp, left: &CType, right: &CType) -> CType {
+    match op {
+        // comparisons and logical operators give an int 0 or 1
+        BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEq | BinaryOp::GreaterEq
+        | BinaryOp::NotEq | BinaryOp::Equal | BinaryOp::LogAnd | BinaryOp::LogOr => CType::Int,
+        // the right operand of a shift doesn't take part, only the left one is promoted
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight => types::common_type(left, left),
+        BinaryOp::Comma => right.clone(),
+        // how many elements apart two pointers are
+        BinaryOp::Subtraction if left.is_pointer() && right.is_pointer() => CType::Long,
+        _ => types::common_type(left, right),
+    }
+}
+
+// a pointer can have an integer added or taken away, be subtracted from the same pointer type
+// and be compared. no other operator takes one
+fn valid_pointer_operands(op: &BinaryOp, left: &CType, right: &CType) -> bool {
+    match op {
+        BinaryOp::Addition => (left.is_pointer() && right.is_integer()) || (left.is_integer() && right.is_pointer()),
+        BinaryOp::Subtraction => (left.is_pointer() && right.is_integer()) || left == right,
+        BinaryOp::Equal | BinaryOp::NotEq | BinaryOp::Less | BinaryOp::LessEq | BinaryOp::Greater | BinaryOp::GreaterEq => {
+            !left.is_floating() && !right.is_floating()
+        }
+        _ => false,
+    }
+}
+
+fn binary_symbol(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Addition => "+",
+        BinaryOp::Subtraction => "-",
+        BinaryOp::Multiplication => "*",
+        BinaryOp::Division => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::ShiftLeft => "<<",
+        BinaryOp::ShiftRight => ">>",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitXor => "^",
+        BinaryOp::Less => "<",
+        BinaryOp::Greater => ">",
+        BinaryOp::LessEq => "<=",
+        BinaryOp::GreaterEq => ">=",
+        BinaryOp::NotEq => "!=",
+        BinaryOp::Equal => "==",
+        BinaryOp::LogAnd => "&&",
+        BinaryOp::LogOr => "||",
+        BinaryOp::Comma => ",",
+    }
+}
+
+fn other_kind_of_symbol(name: &str, span: Span, previous: Span) -> Diagnostic {
+    Diagnostic::error(format!("'{}' redeclared as different kind of symbol", name), span)
+        .with_code("E0306")
+        .with_label(previous, "previous declaration here")
+}
+
+// something that names an object: a variable, *pointer or a member of one. p->x is (*p).x so
+// always one, f().x is a member of a value
+fn is_lvalue(node: &ASTNode) -> bool {
+    match &node.kind {
+        NodeKind::Var(_) | NodeKind::UnaryOp(UnaryOp::Deref, _) => true,
+        NodeKind::Member(base, _) => is_lvalue(base),
+        _ => false,
+    }
+}
+
+// what an assignment takes, and so an initializer, a return and an argument too: numbers convert
+// into each other, a pointer takes the same pointer, a void * or a null pointer constant, and a
+// struct only the same struct
+fn check_assignable(to: &CType, from: &CType, value: &ASTNode) -> Result<(), Diagnostic> {
+    let assignable = if to.is_arithmetic() && from.is_arithmetic() {
+        true
+    } else if let (Some(to_pointee), Some(from_pointee)) = (to.pointee(), from.pointee()) {
+        to_pointee == from_pointee || *to_pointee == CType::Void || *from_pointee == CType::Void
+    } else if to.is_pointer() {
+        is_null_pointer(from, value)
+    } else {
+        to.is_struct() && to == from
+    };
+    if !assignable {
+        return Err(Diagnostic::error(format!("incompatible types when assigning to type '{}' from type '{}'", to, from), value.span)
+            .with_code("E0304"));
+    }
+    Ok(())
+}
+
+// an integer constant 0, which converts to any pointer
+fn is_null_pointer(ty: &CType, value: &ASTNode) -> bool {
+    ty.is_integer() && matches!(constant::eval(value), Some((constant::Value::Int(0), _)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lexer::Lexer, Parser::Parser};
+
+    // what the checker reports about source, errors and warnings
+    fn diagnostics(source: &str, implicit_declarations: bool) -> Vec<Diagnostic> {
+        let mut program = Parser::new(Lexer::new(source)).parse().expect("the program parses");
+        let mut checker = Checker::new();
+        if implicit_declarations {
+            checker.allow_implicit_declarations();
+        }
+        let (Ok((_, diagnostics)) | Err(diagnostics)) = checker.check(&mut program);
+        diagnostics
+    }
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        diagnostics(source, false).iter().filter_map(|diagnostic| diagnostic.code).collect()
+    }
+
+    // the same with --implicit-declarations
+    fn c89_codes(source: &str) -> Vec<&'static str> {
+        diagnostics(source, true).iter().filter_map(|diagnostic| diagnostic.code).collect()
+    }
+
+    #[test]
+    fn names_are_declared_once_per_scope() {
+        assert_eq!(codes("int main(void) { return x; }"), ["E0301"]);
+        assert_eq!(codes("int main(void) { int x; int x; return 0; }"), ["E0302"]);
+        assert!(codes("int main(void) { int x = 1; { int x = 2; } return x; }").is_empty());
+        assert_eq!(codes("int f(int a, int a) { return a; }"), ["E0302"]);
+    }
+
+    #[test]
+    fn a_name_is_in_scope_in_its_own_initializer() {
+        assert!(codes("void *p = &p; int main(void) { void *q = &q; static void *r = &r; return 0; }").is_empty());
+
+        // the inner x is initialized from itself, not from the outer one
+        let mut program = Parser::new(Lexer::new("int main(void) { int x = 1; { int x = x; } return 0; }")).parse().unwrap();
+        assert!(Checker::new().check(&mut program).is_ok());
+        let NodeKind::Program(items) = &program.kind else { unreachable!() };
+        let NodeKind::Function { body: Some(body), .. } = &items[0].kind else { unreachable!() };
+        let NodeKind::Block(block) = &body[1].kind else { unreachable!() };
+        let NodeKind::Declare { name, init: Some(init), .. } = &block[0].kind else { unreachable!() };
+        assert!(matches!(&init.kind, NodeKind::Var(used) if used == name));
+    }
+
+    #[test]
+    fn globals_agree_with_their_other_declarations() {
+        assert!(codes("int x; extern int x; int x = 1;").is_empty());
+        assert_eq!(codes("int x = 1; int x = 2;"), ["E0307"]);
+        assert_eq!(codes("int x; long x;"), ["E0306"]);
+        assert_eq!(codes("static int x; int x;"), ["E0312"]);
+        assert_eq!(codes("int f(void) { return 0; } int f(void) { return 1; }"), ["E0307"]);
+        assert_eq!(codes("int f(int); int f(long);"), ["E0306"]);
+    }
+
+    #[test]
+    fn functions_and_variables_share_file_scope() {
+        assert_eq!(codes("int x; int x(void) { return 0; }"), ["E0306"]);
+        assert_eq!(codes("int f(void); int f = 1;"), ["E0306"]);
+        assert_eq!(codes("int f(void) { return 0; } int main(void) { { int f = 1; return f(); } }"), ["E0326"]);
+        assert_eq!(codes("int f(void) { return 0; } int main(void) { return f + 1; }"), ["E0327"]);
+        assert!(codes("int f(void) { return 0; } int main(void) { { int f = 1; } return f(); }").is_empty());
+    }
+
+    #[test]
+    fn static_storage_comes_out_as_data() {
+        let source = "extern int e; int a[3] = {1, 2}; int *p = &a[2]; extern int a[]; int main(void) { static int n; return n; }";
+        let mut program = Parser::new(Lexer::new(source)).parse().unwrap();
+        let Ok((data, _)) = Checker::new().check(&mut program) else { panic!("{} doesn't check", source) };
+        // e is only declared, the static n gets its unique name
+        let labels: Vec<&str> = data.iter().map(|object| object.label.as_str()).collect();
+        assert_eq!(labels, ["a", "p", "n.1"]);
+        assert_eq!(data[0].init.as_ref().map(Vec::len), Some(2));
+        assert!(data[2].init.is_none());
+        let Some([DataPiece { value: StaticInit::Address(AddressOf::Object(label), 8), .. }]) = data[1].init.as_deref() else {
+            panic!("p doesn't start out as &a[2]");
+        };
+        assert_eq!(label, "a");
+    }
+
+    #[test]
+    fn const_variables_are_not_written() {
+        assert_eq!(codes("int main(void) { const int x = 1; x = 2; return x; }"), ["E0313"]);
+        assert_eq!(codes("const int g = 1; int main(void) { g++; return 0; }"), ["E0313"]);
+        assert_eq!(codes("struct s { int a; }; int main(void) { const struct s v = {1}; v.a = 2; return 0; }"), ["E0313"]);
+    }
+
+    #[test]
+    fn only_lvalues_are_assigned_or_have_an_address() {
+        assert_eq!(codes("int main(void) { 1 = 2; return 0; }"), ["E0209"]);
+        assert_eq!(codes("int main(void) { int x; (x + 1)++; return 0; }"), ["E0209"]);
+        assert_eq!(codes("int main(void) { int *p = &3; return 0; }"), ["E0209"]);
+        assert_eq!(codes("int main(void) { int a[2], b[2]; a = b; return 0; }"), ["E0209"]);
+
+        let header = "struct s { int x; }; struct s f(void); struct s v, *p; ";
+        let body = |stmts: &str| codes(&format!("{}int main(void) {{ {} return 0; }}", header, stmts));
+        assert!(body("v.x = 1; p->x = 2; f().x; int *q = &p->x; q = &v.x;").is_empty());
+        assert_eq!(body("f().x = 3;"), ["E0209"]);
+        assert_eq!(body("f().x++;"), ["E0209"]);
+        assert_eq!(body("int *q = &f().x;"), ["E0209"]);
+        assert_eq!(body("(1 ? v : v).x = 4;"), ["E0209"]);
+    }
+
+    #[test]
+    fn void_values_are_not_used() {
+        let void = "void v(void) {} ";
+        assert_eq!(codes(&format!("{}int main(void) {{ return v() + 1; }}", void)), ["E0323"]);
+        assert_eq!(codes(&format!("{}int main(void) {{ int x = v(); return x; }}", void)), ["E0323"]);
+        assert_eq!(codes(&format!("{}int main(void) {{ if (v()) return 1; return 0; }}", void)), ["E0323"]);
+        assert!(codes(&format!("{}int main(void) {{ v(), 1; (void) 2; return 0; }}", void)).is_empty());
+    }
+
+    #[test]
+    fn casts_are_between_scalars() {
+        let header = "struct s { int a; }; struct s v; int *p; double d; ";
+        assert_eq!(codes(&format!("{}int main(void) {{ (struct s) 1; return 0; }}", header)), ["E0324"]);
+        assert_eq!(codes(&format!("{}int main(void) {{ return (int) v; }}", header)), ["E0324"]);
+        assert_eq!(codes(&format!("{}int main(void) {{ (double) p; (int *) d; return 0; }}", header)), ["E0324", "E0324"]);
+        assert!(codes(&format!("{}int main(void) {{ (long) p; (char *) 1; (void) v; return (int) d; }}", header)).is_empty());
+    }
+
+    #[test]
+    fn assignments_initializers_returns_and_arguments_follow_one_rule() {
+        let header = "struct s { int a; }; struct t { int a; }; struct s v; int i; double d; int *p; char *c; void *any; ";
+        let body = |stmts: &str| codes(&format!("{}int main(void) {{ {} return 0; }}", header, stmts));
+        assert!(body("i = d; d = i; p = any; any = c; p = 0; p = (void *) 0; c = \"x\"; v = v;").is_empty());
+        assert_eq!(body("p = 1.5;"), ["E0304"]);
+        assert_eq!(body("double e = p;"), ["E0304"]);
+        assert_eq!(body("int j = p;"), ["E0304"]);
+        assert_eq!(body("int *q = c;"), ["E0304"]);
+        assert_eq!(body("p = 1;"), ["E0304"]);
+        assert_eq!(body("struct t w = v;"), ["E0304"]);
+        assert_eq!(codes("double f(int *p) { return p; }"), ["E0304"]);
+        assert!(codes("int *f(void) { return 0; }").is_empty());
+        assert_eq!(codes("void g(int *p); int main(void) { char c; g(&c); g(0); return 0; }"), ["E0310"]);
+        assert_eq!(codes("void g(double d); int main(void) { g(1); g(\"x\"); return 0; }"), ["E0310"]);
+    }
+
+    #[test]
+    fn conditions_are_scalars() {
+        let header = "struct s { int a; }; struct s v, w; int *p; ";
+        let body = |stmts: &str| codes(&format!("{}void f(void); int main(void) {{ {} return 0; }}", header, stmts));
+        assert!(body("if (p) ; while (0) ; for (; 1.5; ) ; !p; p && 1.5; 1 ? v : w; 1 ? f() : f();").is_empty());
+        assert_eq!(body("if (v) ;"), ["E0304"]);
+        assert_eq!(body("do ; while (v);"), ["E0304"]);
+        assert_eq!(body("!v;"), ["E0304"]);
+        assert_eq!(body("v && 1;"), ["E0304"]);
+        assert_eq!(body("1 || v;"), ["E0304"]);
+        assert_eq!(body("v ? 1 : 2;"), ["E0304"]);
+    }
+
+    #[test]
+    fn conditional_arms_have_a_common_type() {
+        let header = "struct s { int a; }; struct s v; int *p; void *any; char *c; ";
+        let body = |stmts: &str| codes(&format!("{}void f(void); int main(void) {{ {} return 0; }}", header, stmts));
+        assert!(body("int *q = 1 ? p : 0; q = 1 ? 0 : p; q = 1 ? p : any; char *d = 1 ? any : c; double e = 1 ? 1 : 2.5;").is_empty());
+        assert_eq!(body("1 ? v : 1;"), ["E0304"]);
+        assert_eq!(body("1 ? p : 1.0;"), ["E0304"]);
+        assert_eq!(body("1 ? p : c;"), ["E0304"]);
+        assert_eq!(body("1 ? p : 1;"), ["E0304"]);
+        assert_eq!(body("1 ? f() : 1;"), ["E0304"]);
+    }
+
+    #[test]
+    fn returns_match_the_function() {
+        assert_eq!(codes("void f(void) { return 1; }"), ["E0325"]);
+        assert_eq!(codes("int f(void) { return; }"), ["E0325"]);
+        assert!(codes("void g(void); void f(void) { return g(); }").is_empty());
+        assert_eq!(codes("void g(void); int f(void) { return g(); }"), ["E0323"]);
+        assert_eq!(codes("struct s { int a; }; struct s v; int f(void) { return v; }"), ["E0304"]);
+    }
+
+    #[test]
+    fn declarations_of_a_function_agree() {
+        assert!(codes("int f(int a, double b); int f(int a, double b) { return a; }").is_empty());
+        assert_eq!(codes("int f(int a); int f(long a) { return 0; }"), ["E0306"]);
+        assert_eq!(codes("int f(int a); double f(int a);"), ["E0306"]);
+        assert_eq!(codes("int f(int a, ...); int f(int a);"), ["E0306"]);
+        assert_eq!(codes("int f(void) { return 0; } int f(void) { return 1; }"), ["E0307"]);
+        // f() is f(void), unless it is C89
+        assert!(codes("int f(); int f(void) { return 0; }").is_empty());
+        assert_eq!(codes("int f(); int f(int a) { return a; }"), ["E0306"]);
+        assert!(c89_codes("int f(); int f(int a) { return a; } int g(double d); int g();").is_empty());
+        assert_eq!(c89_codes("int f(); long f(int a);"), ["E0306"]);
+        // a float or char argument of f() arrives promoted, so it can't match those
+        assert_eq!(c89_codes("int f(); int f(float x);"), ["E0306"]);
+        assert_eq!(c89_codes("int f(char c); int f();"), ["E0306"]);
+    }
+
+    #[test]
+    fn calls_have_as_many_arguments_as_parameters() {
+        assert!(codes("int f(int a, int b); int main(void) { return f(1, 2); }").is_empty());
+        assert_eq!(codes("int f(int a, int b); int main(void) { return f(1); }"), ["E0309"]);
+        assert_eq!(codes("int f(int a); int main(void) { return f(1, 2); }"), ["E0309"]);
+        assert!(codes("int f(int a, ...); int main(void) { return f(1, 2, 3); }").is_empty());
+        assert_eq!(codes("int f(int a, ...); int main(void) { return f(); }"), ["E0309"]);
+        // an int f(); said earlier doesn't undo what the prototype says
+        assert_eq!(c89_codes("int f(int a); int f(); int main(void) { return f(); }"), ["E0309"]);
+    }
+
+    #[test]
+    fn empty_parentheses_leave_the_arguments_unchecked_in_c89() {
+        let source = "int f(); int main(void) { return f(1) + f(2.0, \"x\"); }";
+        assert!(c89_codes(source).is_empty());
+
+        let reported = diagnostics(source, false);
+        assert_eq!(reported.iter().filter_map(|diagnostic| diagnostic.code).collect::<Vec<_>>(), ["E0309", "E0309"]);
+        assert!(reported[0].notes.iter().any(|note| note.contains("'f(void)'") && note.contains("--implicit-declarations")));
+        // a prototype that says (void) gets no such note
+        let reported = diagnostics("int f(void); int main(void) { return f(1); }", false);
+        assert!(reported[0].notes.iter().all(|note| !note.contains("--implicit-declarations")));
+    }
+}
@@ -0,0 +1,534 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+// The C types the compiler knows about.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CType {
+    Void,
+    Char, // plain char is signed on x86-64, so it doubles as signed char
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    LongLong,
+    ULongLong,
+    Float,
+    Double,
+    Pointer(Box<CType>),
+    Array(Box<CType>, u64), // element type and count. 0 is int a[], the initializer says how many
+    Struct(StructRef), // struct and union
+}
+
+// a struct or union type. every `struct point` naming the same tag shares one of these, so a
+// pointer to a struct that is only declared yet sees the members once it is defined
+#[derive(Clone)]
+pub struct StructRef(Rc<RefCell<StructDef>>);
+
+struct StructDef {
+    union: bool,
+    tag: Option<String>,
+    members: Option<Vec<Member>>, // None until the { } is seen
+    size: u64,
+    align: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub ty: CType,
+    pub offset: u64,
+}
+
+impl StructRef {
+    // `struct tag`, with the members still unknown
+    pub fn declare(union: bool, tag: Option<String>) -> StructRef {
+        StructRef(Rc::new(RefCell::new(StructDef { union, tag, members: None, size: 0, align: 1 })))
+    }
+
+    pub fn is_union(&self) -> bool {
+        self.0.borrow().union
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.0.borrow().members.is_some()
+    }
+
+    // lays the members out the way the System V ABI does: each at the next multiple of its
+    // alignment, the size rounded up to the largest alignment. union members all start at 0
+    pub fn define(&self, members: Vec<(String, CType)>) {
+        let mut def = self.0.borrow_mut();
+        let (mut size, mut align) = (0u64, 1u64);
+        let mut laid_out = Vec::new();
+        for (name, ty) in members {
+            let member_align = ty.align().max(1);
+            let offset = if def.union { 0 } else { size.div_ceil(member_align) * member_align };
+            size = size.max(offset + ty.size());
+            align = align.max(member_align);
+            laid_out.push(Member { name, ty, offset });
+        }
+        def.size = size.div_ceil(align) * align;
+        def.align = align;
+        def.members = Some(laid_out);
+    }
+
+    pub fn member(&self, name: &str) -> Option<Member> {
+        self.0.borrow().members.as_ref()?.iter().find(|member| member.name == name).cloned()
+    }
+
+    pub fn members(&self) -> Vec<Member> {
+        self.0.borrow().members.clone().unwrap_or_default()
+    }
+}
+
+// two struct types are the same type only if they come from the same declaration
+impl PartialEq for StructRef {
+    fn eq(&self, other: &StructRef) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// not derived: a struct with a pointer to itself would print forever
+impl fmt::Debug for StructRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", CType::Struct(self.clone()))
+    }
+}
+
+impl CType {
+    // size in bytes on x86-64 (System V)
+    pub fn size(&self) -> u64 {
+        match self {
+            CType::Void | CType::Char | CType::UChar => 1, // void is 1 like in gcc, so void * arithmetic works
+            CType::Short | CType::UShort => 2,
+            CType::Int | CType::UInt | CType::Float => 4,
+            CType::Long | CType::ULong | CType::LongLong | CType::ULongLong | CType::Double => 8,
+            CType::Pointer(_) => 8,
+            CType::Array(element, count) => element.size() * count,
+            CType::Struct(def) => def.0.borrow().size,
+        }
+    }
+
+    // the address of a value of this type is a multiple of this
+    pub fn align(&self) -> u64 {
+        match self {
+            CType::Array(element, _) => element.align(),
+            CType::Struct(def) => def.0.borrow().align,
+            _ => self.size(),
+        }
+    }
+
+    pub fn is_struct(&self) -> bool {
+        matches!(self, CType::Struct(_))
+    }
+
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, CType::Char | CType::Short | CType::Int | CType::Long | CType::LongLong)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            CType::Char | CType::UChar | CType::Short | CType::UShort | CType::Int | CType::UInt
+                | CType::Long | CType::ULong | CType::LongLong | CType::ULongLong
+        )
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self, CType::Float | CType::Double)
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        self.is_integer() || self.is_floating()
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, CType::Pointer(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, CType::Array(..))
+    }
+
+    // an array used as a value is a pointer to its first element
+    pub fn decay(&self) -> CType {
+        match self {
+            CType::Array(element, _) => element.as_ref().clone().pointer_to(),
+            other => other.clone(),
+        }
+    }
+
+    // integer conversion rank, C11 6.3.1.1
+    fn rank(&self) -> u8 {
+        match self {
+            CType::Char | CType::UChar => 1,
+            CType::Short | CType::UShort => 2,
+            CType::Int | CType::UInt => 3,
+            CType::Long | CType::ULong => 4,
+            CType::LongLong | CType::ULongLong => 5,
+            _ => 0,
+        }
+    }
+
+    // biggest value the type can hold
+    pub fn max_value(&self) -> u64 {
+        let bits = self.size() * 8;
+        if self.is_signed() {
+            (1u64 << (bits - 1)) - 1
+        } else if bits == 64 {
+            u64::MAX
+        } else {
+            (1u64 << bits) - 1
+        }
+    }
+
+    pub fn pointer_to(self) -> CType {
+        CType::Pointer(Box::new(self))
+    }
+
+    // what a pointer points to, None for anything else
+    pub fn pointee(&self) -> Option<&CType> {
+        match self {
+            CType::Pointer(pointee) => Some(pointee),
+            _ => None,
+        }
+    }
+
+    fn to_unsigned(&self) -> CType {
+        match self {
+            CType::Char | CType::UChar => CType::UChar,
+            CType::Short | CType::UShort => CType::UShort,
+            CType::Int | CType::UInt => CType::UInt,
+            CType::Long | CType::ULong => CType::ULong,
+            CType::LongLong | CType::ULongLong => CType::ULongLong,
+            other => other.clone(),
+        }
+    }
+}
+
+impl CType {
+    // the innermost element type of an array and its [n] parts, in source order
+    fn dimensions(&self) -> (&CType, String) {
+        let mut ty = self;
+        let mut dimensions = String::new();
+        while let CType::Array(element, count) = ty {
+            if *count == 0 {
+                dimensions.push_str("[]");
+            } else {
+                dimensions.push_str(&format!("[{}]", count));
+            }
+            ty = element;
+        }
+        (ty, dimensions)
+    }
+}
+
+impl fmt::Display for CType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            // int[2][3], and int (*)[3] for a pointer to an array
+            CType::Pointer(pointee) if pointee.is_array() => {
+                let (element, dimensions) = pointee.dimensions();
+                return write!(f, "{} (*){}", element, dimensions);
+            }
+            CType::Array(..) => {
+                let (element, dimensions) = self.dimensions();
+                return write!(f, "{}{}", element, dimensions);
+            }
+            CType::Pointer(pointee) => return write!(f, "{} *", pointee),
+            CType::Struct(def) => {
+                let def = def.0.borrow();
+                let keyword = if def.union { "union" } else { "struct" };
+                return write!(f, "{} {}", keyword, def.tag.as_deref().unwrap_or("<anonymous>"));
+            }
+            CType::Void => "void",
+            CType::Char => "char",
+            CType::UChar => "unsigned char",
+            CType::Short => "short",
+            CType::UShort => "unsigned short",
+            CType::Int => "int",
+            CType::UInt => "unsigned int",
+            CType::Long => "long",
+            CType::ULong => "unsigned long",
+            CType::LongLong => "long long",
+            CType::ULongLong => "unsigned long long",
+            CType::Float => "float",
+            CType::Double => "double",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+
+// the usual arithmetic conversions (C11 6.3.1.8): the type both operands of a binary operator
+// are converted to before the operation
+pub fn common_type(left: &CType, right: &CType) -> CType {
+    // a pointer stays a pointer: p + 1, c ? p : 0
+    if left.is_pointer() {
+        return left.clone();
+    }
+    if right.is_pointer() {
+        return right.clone();
+    }
+    let (left, right) = (promote(left), promote(right));
+    if left == CType::Double || right == CType::Double {
+        return CType::Double;
+    }
+    if left == CType::Float || right == CType::Float {
+        return CType::Float;
+    }
+    if left == right {
+        return left;
+    }
+    if left.is_signed() == right.is_signed() {
+        return if left.rank() >= right.rank() { left } else { right };
+    }
+    let (signed, unsigned) = if left.is_signed() { (left, right) } else { (right, left) };
+    if unsigned.rank() >= signed.rank() {
+        unsigned
+    } else if signed.size() > unsigned.size() {
+        // the signed type can hold every value of the unsigned one
+        signed
+    } else {
+        signed.to_unsigned()
+    }
+}
+
+
+// the integer promotions (C11 6.3.1.1p2): anything ranked below int works as an int. all of
+// char, unsigned char, short and unsigned short fit in one
+fn promote(ty: &CType) -> CType {
+    if ty.is_integer() && ty.rank() < CType::Int.rank() {
+        CType::Int
+    } else {
+        ty.clone()
+    }
+}
+
+
+// System V classification of a struct passed or returned by value (AMD64 ABI 3.2.3)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgClass {
+    Integer, // the eightbyte goes in a general purpose register
+    Sse,     // only float and double in it, it goes in an xmm register
+}
+
+// the class of each eightbyte of a struct, or None when it is passed in memory: it is bigger
+// than 16 bytes, or has a member that isn't aligned
+pub fn classify(ty: &CType) -> Option<Vec<ArgClass>> {
+    let size = ty.size();
+    if size > 16 || size == 0 {
+        return None;
+    }
+    let mut classes = vec![ArgClass::Sse; size.div_ceil(8) as usize];
+    classify_fields(ty, 0, &mut classes)?;
+    Some(classes)
+}
+
+// an eightbyte stays SSE only if every scalar in it is a float or a double
+fn classify_fields(ty: &CType, offset: u64, classes: &mut [ArgClass]) -> Option<()> {
+    match ty {
+        CType::Struct(def) => {
+            for member in def.members() {
+                classify_fields(&member.ty, offset + member.offset, classes)?;
+            }
+        }
+        CType::Array(element, count) => {
+            for index in 0..*count {
+                classify_fields(element, offset + index * element.size(), classes)?;
+            }
+        }
+        _ => {
+            if !offset.is_multiple_of(ty.align().max(1)) {
+                return None;
+            }
+            if !ty.is_floating() {
+                classes[(offset / 8) as usize] = ArgClass::Integer;
+            }
+        }
+    }
+    Some(())
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Radix {
+    Decimal,
+    Octal,
+    Hex,
+    Binary,
+}
+
+// the u/l/ll part of an integer literal, in any of the orders C allows (ul, lu, ull, llu...)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IntSuffix {
+    pub unsigned: bool,
+    pub longs: u8, // 0, 1 for l, 2 for ll
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatSuffix {
+    None,
+    F,
+    L,
+}
+
+// 1.0 is a double, 1.0f a float. we have no long double, 1.0L is treated as a double
+pub fn float_literal_type(suffix: FloatSuffix) -> CType {
+    match suffix {
+        FloatSuffix::F => CType::Float,
+        FloatSuffix::None | FloatSuffix::L => CType::Double,
+    }
+}
+
+
+// C11 6.4.4.1p5: the type of an integer literal is the first type of its list the value fits in.
+// Decimal literals without a u suffix only try signed types, the other radixes also try the
+// unsigned type of each rank. None means the value doesn't fit anywhere.
+pub fn integer_literal_type(value: u64, radix: Radix, suffix: IntSuffix) -> Option<CType> {
+    let ranks: &[CType] = match suffix.longs {
+        0 => &[CType::Int, CType::Long, CType::LongLong],
+        1 => &[CType::Long, CType::LongLong],
+        _ => &[CType::LongLong],
+    };
+
+    let mut candidates = Vec::new();
+    for rank in ranks {
+        let unsigned = rank.to_unsigned();
+        if suffix.unsigned {
+            candidates.push(unsigned);
+        } else {
+            candidates.push(rank.clone());
+            if radix != Radix::Decimal {
+                candidates.push(unsigned);
+            }
+        }
+    }
+
+    candidates.into_iter().find(|ty| value <= ty.max_value())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(value: u64, radix: Radix, unsigned: bool, longs: u8) -> Option<CType> {
+        integer_literal_type(value, radix, IntSuffix { unsigned, longs })
+    }
+
+    #[test]
+    fn decimal_literals_only_try_signed_types() {
+        assert_eq!(literal(2147483647, Radix::Decimal, false, 0), Some(CType::Int));
+        assert_eq!(literal(4294967295, Radix::Decimal, false, 0), Some(CType::Long));
+        assert_eq!(literal(9223372036854775807, Radix::Decimal, false, 0), Some(CType::Long));
+        assert_eq!(literal(9223372036854775808, Radix::Decimal, false, 0), None);
+    }
+
+    #[test]
+    fn other_radixes_also_try_unsigned_types() {
+        assert_eq!(literal(0xFFFFFFFF, Radix::Hex, false, 0), Some(CType::UInt));
+        assert_eq!(literal(0x100000000, Radix::Hex, false, 0), Some(CType::Long));
+        assert_eq!(literal(0o37777777777, Radix::Octal, false, 0), Some(CType::UInt));
+        assert_eq!(literal(u64::MAX, Radix::Binary, false, 0), Some(CType::ULong));
+    }
+
+    #[test]
+    fn suffixes_skip_types() {
+        assert_eq!(literal(1, Radix::Decimal, true, 0), Some(CType::UInt));
+        assert_eq!(literal(4294967296, Radix::Decimal, true, 0), Some(CType::ULong));
+        assert_eq!(literal(1, Radix::Decimal, false, 1), Some(CType::Long));
+        assert_eq!(literal(1, Radix::Hex, false, 2), Some(CType::LongLong));
+        assert_eq!(literal(u64::MAX, Radix::Decimal, true, 2), Some(CType::ULongLong));
+    }
+
+    #[test]
+    fn common_type_promotes_then_converts() {
+        assert_eq!(common_type(&CType::Char, &CType::UShort), CType::Int);
+        assert_eq!(common_type(&CType::Int, &CType::UInt), CType::UInt);
+        assert_eq!(common_type(&CType::Long, &CType::UInt), CType::Long);
+        assert_eq!(common_type(&CType::LongLong, &CType::ULong), CType::ULongLong);
+        assert_eq!(common_type(&CType::ULong, &CType::Int), CType::ULong);
+        assert_eq!(common_type(&CType::Float, &CType::Long), CType::Float);
+        assert_eq!(common_type(&CType::Float, &CType::Double), CType::Double);
+        assert_eq!(common_type(&CType::Int.pointer_to(), &CType::Long), CType::Int.pointer_to());
+    }
+
+    // a struct, or a union, defined with these members
+    fn defined(union: bool, members: &[(&str, CType)]) -> CType {
+        let def = StructRef::declare(union, None);
+        def.define(members.iter().map(|(name, ty)| (name.to_string(), ty.clone())).collect());
+        CType::Struct(def)
+    }
+
+    fn offsets(ty: &CType) -> Vec<u64> {
+        let CType::Struct(def) = ty else { unreachable!() };
+        def.members().iter().map(|member| member.offset).collect()
+    }
+
+    #[test]
+    fn members_are_aligned_and_the_size_is_padded() {
+        let s = defined(false, &[("c", CType::Char), ("i", CType::Int), ("d", CType::Char)]);
+        assert_eq!(offsets(&s), [0, 4, 8]);
+        assert_eq!((s.size(), s.align()), (12, 4));
+
+        let s = defined(false, &[("c", CType::Char), ("d", CType::Double)]);
+        assert_eq!(offsets(&s), [0, 8]);
+        assert_eq!((s.size(), s.align()), (16, 8));
+
+        // chars need no padding, an array is aligned like its element
+        let s = defined(false, &[("a", CType::Char), ("b", CType::Char), ("c", CType::Char)]);
+        assert_eq!((s.size(), s.align()), (3, 1));
+        let s = defined(false, &[("a", CType::Array(Box::new(CType::Short), 3)), ("i", CType::Int)]);
+        assert_eq!(offsets(&s), [0, 8]);
+        assert_eq!((s.size(), s.align()), (12, 4));
+    }
+
+    #[test]
+    fn nested_structs_keep_their_own_alignment() {
+        let inner = defined(false, &[("c", CType::Char), ("d", CType::Double)]);
+        let outer = defined(false, &[("c", CType::Char), ("inner", inner), ("i", CType::Int)]);
+        assert_eq!(offsets(&outer), [0, 8, 24]);
+        assert_eq!((outer.size(), outer.align()), (32, 8));
+    }
+
+    #[test]
+    fn union_members_share_the_start() {
+        let u = defined(true, &[("c", CType::Array(Box::new(CType::Char), 5)), ("i", CType::Int)]);
+        assert_eq!(offsets(&u), [0, 0]);
+        assert_eq!((u.size(), u.align()), (8, 4));
+    }
+
+    #[test]
+    fn eightbytes_of_integers_or_floats_are_classified_alone() {
+        use ArgClass::{Integer, Sse};
+        assert_eq!(classify(&defined(false, &[("a", CType::Long), ("b", CType::Long)])), Some(vec![Integer, Integer]));
+        assert_eq!(classify(&defined(false, &[("x", CType::Double), ("y", CType::Double)])), Some(vec![Sse, Sse]));
+        assert_eq!(classify(&defined(false, &[("x", CType::Float), ("y", CType::Float)])), Some(vec![Sse]));
+        assert_eq!(classify(&defined(false, &[("c", CType::Array(Box::new(CType::Char), 16))])), Some(vec![Integer, Integer]));
+    }
+
+    #[test]
+    fn an_integer_makes_its_whole_eightbyte_integer() {
+        use ArgClass::{Integer, Sse};
+        assert_eq!(classify(&defined(false, &[("i", CType::Int), ("f", CType::Float)])), Some(vec![Integer]));
+        assert_eq!(classify(&defined(false, &[("d", CType::Double), ("i", CType::Int)])), Some(vec![Sse, Integer]));
+        assert_eq!(classify(&defined(false, &[("i", CType::Int), ("d", CType::Double)])), Some(vec![Integer, Sse]));
+        assert_eq!(classify(&defined(true, &[("f", CType::Float), ("i", CType::Int)])), Some(vec![Integer]));
+        // members of a nested struct count where they land
+        let pair = defined(false, &[("x", CType::Float), ("y", CType::Float)]);
+        assert_eq!(classify(&defined(false, &[("p", pair), ("n", CType::Long)])), Some(vec![Sse, Integer]));
+    }
+
+    #[test]
+    fn big_structs_go_in_memory() {
+        assert_eq!(classify(&defined(false, &[("a", CType::Long), ("b", CType::Long), ("c", CType::Long)])), None);
+        assert_eq!(classify(&defined(false, &[("d", CType::Array(Box::new(CType::Double), 3))])), None);
+        assert_eq!(classify(&CType::Struct(StructRef::declare(false, None))), None);
+    }
+}